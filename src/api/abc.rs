@@ -0,0 +1,45 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::Data;
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::abc::{AbcAnalysisRequest, AbcClassification, ProductAbcClass};
+
+use validator::Validate;
+
+//POST / recompute ABC classes of products by outbound volume or value
+#[utoipa::path(
+    tag = "abc",
+    responses(
+        (status = 200, description = "Recomputed ABC classes", body = Vec<AbcClassification>),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/abc_analysis")]
+async fn recompute_abc_classification(db: Data<Database>, tenant: Tenant, body: Json<AbcAnalysisRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let classification = db.recompute_abc_classification(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().json(classification))
+}
+
+//GET / stored ABC classes of products
+#[utoipa::path(
+    tag = "abc",
+    responses(
+        (status = 200, description = "ABC class per product", body = Vec<ProductAbcClass>),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/abc_analysis")]
+async fn get_abc_classification(db: Data<Database>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
+
+    let classes = db.get_abc_classification(&tenant).await?;
+
+    Ok(HttpResponse::Ok().json(classes))
+}
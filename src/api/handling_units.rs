@@ -0,0 +1,135 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::handling_units::{CreateHandlingUnitRequest, GetHandlingUnit, MoveHandlingUnitRequest, NestHandlingUnitRequest, PackHandlingUnitRequest, HandlingUnitDetails};
+
+use validator::Validate;
+
+//GET / handling unit with contents and nested handling units
+#[utoipa::path(
+    tag = "handling_units",
+    params(GetHandlingUnit),
+    responses(
+        (status = 200, description = "Handling unit with contents and nested units", body = HandlingUnitDetails),
+        (status = 404, description = "Handling unit not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/handling_units/{lpn}")]
+async fn get_handling_unit(db: Data<Database>, tenant: Tenant, lpn: Path<GetHandlingUnit>) -> Result<HttpResponse, ApiError> {
+
+    lpn.validate()?;
+
+    let details = db.get_handling_unit(&tenant, &lpn).await?
+        .ok_or_else(|| ApiError::NotFound("Handling unit not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(details))
+}
+
+//POST / create handling unit
+#[utoipa::path(
+    tag = "handling_units",
+    responses(
+        (status = 201, description = "Handling unit created", body = String, content_type = "text/plain"),
+        (status = 404, description = "Parent handling unit not found", body = ErrorBody),
+        (status = 409, description = "LPN already exists", body = ErrorBody),
+        (status = 422, description = "Validation failed, or the parent is at another location", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/handling_units")]
+async fn create_handling_unit(db: Data<Database>, tenant: Tenant, body: Json<CreateHandlingUnitRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.create_handling_unit(&tenant, &body).await?;
+
+    Ok(HttpResponse::Created().body("Handling unit created successfully!"))
+}
+
+//POST / pack loose stock into handling unit
+#[utoipa::path(
+    tag = "handling_units",
+    responses(
+        (status = 200, description = "Stock packed", body = String, content_type = "text/plain"),
+        (status = 404, description = "Handling unit or stock row not found", body = ErrorBody),
+        (status = 409, description = "Not enough loose pcs at the location", body = ErrorBody),
+        (status = 422, description = "Validation failed, or the product is serial tracked", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/handling_units/pack")]
+async fn pack_handling_unit(db: Data<Database>, tenant: Tenant, body: Json<PackHandlingUnitRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.pack_handling_unit(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Stock packed successfully!"))
+}
+
+//POST / unpack stock from handling unit
+#[utoipa::path(
+    tag = "handling_units",
+    responses(
+        (status = 200, description = "Stock unpacked", body = String, content_type = "text/plain"),
+        (status = 404, description = "Handling unit not found, or the product is not packed in it", body = ErrorBody),
+        (status = 409, description = "Not enough pcs in the handling unit", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/handling_units/unpack")]
+async fn unpack_handling_unit(db: Data<Database>, tenant: Tenant, body: Json<PackHandlingUnitRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.unpack_handling_unit(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Stock unpacked successfully!"))
+}
+
+//POST / nest handling unit into another one
+#[utoipa::path(
+    tag = "handling_units",
+    responses(
+        (status = 200, description = "Handling unit nested", body = String, content_type = "text/plain"),
+        (status = 404, description = "Handling unit or parent not found", body = ErrorBody),
+        (status = 422, description = "Validation failed, or the units are at different locations", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/handling_units/nest")]
+async fn nest_handling_unit(db: Data<Database>, tenant: Tenant, body: Json<NestHandlingUnitRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.nest_handling_unit(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Handling unit nested successfully!"))
+}
+
+//POST / move handling unit with everything in it to another location
+#[utoipa::path(
+    tag = "handling_units",
+    responses(
+        (status = 200, description = "Handling unit moved", body = String, content_type = "text/plain"),
+        (status = 404, description = "Handling unit not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/handling_units/move")]
+async fn move_handling_unit(db: Data<Database>, tenant: Tenant, body: Json<MoveHandlingUnitRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.move_handling_unit(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Handling unit moved successfully!"))
+}
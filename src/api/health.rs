@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::db::database::Database;
+use crate::models::health::Readiness;
+
+use actix_web::web::Data;
+use actix_web::{get, Responder, HttpResponse};
+
+// /readyz answers within this time even when MySQL hangs
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+//GET / process is alive, does not touch the database
+#[utoipa::path(
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Process is alive", body = Object),
+    ),
+)]
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+//GET / database reachable, schema version matches and pool usage, 503 when not ready
+#[utoipa::path(
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Ready to serve requests", body = Readiness),
+        (status = 503, description = "Database unreachable or schema version differs", body = Readiness),
+    ),
+)]
+#[get("/readyz")]
+async fn readyz(db: Data<Database>) -> impl Responder {
+    let readiness = db.readiness(READINESS_TIMEOUT).await;
+
+    match readiness.ready {
+        true => HttpResponse::Ok().json(readiness),
+        false => HttpResponse::ServiceUnavailable().json(readiness),
+    }
+}
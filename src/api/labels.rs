@@ -0,0 +1,94 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::labels::{svg, zpl, LabelFormat};
+use crate::models::labels::{GetLocationLabel, GetProductLabel, LabelQuery, LocationRangeLabelRequest};
+
+use validator::Validate;
+
+//GET / product label with barcode of the product_code, name and variant attributes
+#[utoipa::path(
+    tag = "labels",
+    params(GetProductLabel, LabelQuery),
+    responses(
+        (status = 200, description = "Label as SVG or ZPL", content(
+            (String = "image/svg+xml"),
+            (String = "application/x-zpl"),
+        )),
+        (status = 400, description = "Product code can not be encoded", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/labels/product/{product_code}")]
+async fn get_product_label(db: Data<Database>, tenant: Tenant, product: Path<GetProductLabel>, query: Query<LabelQuery>) -> Result<HttpResponse, ApiError> {
+
+    product.validate()?;
+
+    let found_product = db.get_product_by_code(&tenant, &product.product_code).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+    let label = match query.format {
+        LabelFormat::Svg => svg::product_label(&found_product)?,
+        LabelFormat::Zpl => zpl::product_label(&found_product),
+    };
+
+    Ok(HttpResponse::Ok().content_type(query.format.content_type()).body(label))
+}
+
+//GET / location label with Code128 or QR code of warehouse+location
+#[utoipa::path(
+    tag = "labels",
+    params(GetLocationLabel, LabelQuery),
+    responses(
+        (status = 200, description = "Label as SVG or ZPL", content(
+            (String = "image/svg+xml"),
+            (String = "application/x-zpl"),
+        )),
+        (status = 400, description = "Location can not be encoded", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+)]
+#[get("/labels/location/{warehouse}/{location}")]
+async fn get_location_label(location: Path<GetLocationLabel>, query: Query<LabelQuery>) -> Result<HttpResponse, ApiError> {
+
+    location.validate()?;
+
+    let label = match query.format {
+        LabelFormat::Svg => svg::location_label(&location.warehouse, &location.location, query.symbology)?,
+        LabelFormat::Zpl => zpl::location_label(&location.warehouse, &location.location, query.symbology),
+    };
+
+    Ok(HttpResponse::Ok().content_type(query.format.content_type()).body(label))
+}
+
+//POST / batch of location labels for a location range
+#[utoipa::path(
+    tag = "labels",
+    responses(
+        (status = 200, description = "Labels as SVG or ZPL", content(
+            (String = "image/svg+xml"),
+            (String = "application/x-zpl"),
+        )),
+        (status = 400, description = "Location can not be encoded", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+)]
+#[post("/labels/locations")]
+async fn get_location_range_labels(body: Json<LocationRangeLabelRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let locations = body.locations();
+    let labels = match body.format {
+        LabelFormat::Svg => svg::location_labels(&body.warehouse, &locations, body.symbology)?,
+        LabelFormat::Zpl => zpl::location_labels(&body.warehouse, &locations, body.symbology),
+    };
+
+    Ok(HttpResponse::Ok().content_type(body.format.content_type()).body(labels))
+}
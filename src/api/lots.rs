@@ -0,0 +1,60 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::incoming::{GetCodeAndPcsForSalesOrder, UniqueIdentifier};
+use crate::models::lots::{GetExpiringStock, StockAllocation};
+use crate::models::packaging::StockQuery;
+
+use validator::Validate;
+
+//GET / stock rows expiring within N days
+#[utoipa::path(
+    tag = "lots",
+    params(GetExpiringStock, StockQuery),
+    responses(
+        (status = 200, description = "Stock rows expiring within the days", body = Vec<UniqueIdentifier>),
+        (status = 404, description = "No stock expiring", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/unique_identifiers_expiring/{days}")]
+async fn get_expiring_stock(db: Data<Database>, tenant: Tenant, request: Path<GetExpiringStock>, query: Query<StockQuery>) -> Result<HttpResponse, ApiError> {
+
+    request.validate()?;
+
+    let mut locations = db.get_expiring_stock(&tenant, &request).await?;
+    if locations.is_empty() {
+        return Err(ApiError::NotFound("No stock expiring in the specified period.".to_string()));
+    }
+
+    if query.breakdown {
+        db.attach_breakdown(&tenant, &mut locations).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(locations))
+}
+
+//POST / FEFO allocation of product_code and pcs for sales order
+#[utoipa::path(
+    tag = "lots",
+    responses(
+        (status = 200, description = "FEFO allocation", body = Vec<StockAllocation>),
+        (status = 409, description = "Not enough pcs in stock", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/allocate_sales_order")]
+async fn allocate_sales_order(db: Data<Database>, tenant: Tenant, body: Json<GetCodeAndPcsForSalesOrder>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let allocations = db.allocate_fefo(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().json(allocations))
+}
@@ -0,0 +1,24 @@
+use crate::db::database::Database;
+use crate::metrics::Metrics;
+use crate::models::errors::{ApiError, ErrorBody};
+use crate::models::tenant::Tenant;
+
+use actix_web::web::Data;
+use actix_web::{get, HttpResponse};
+
+//GET / Prometheus text format, HTTP and pool metrics plus the stock metrics of the tenant
+#[utoipa::path(
+    tag = "metrics",
+    responses(
+        (status = 200, description = "Prometheus text format", body = String, content_type = "text/plain; version=0.0.4"),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/metrics")]
+async fn get_metrics(db: Data<Database>, metrics: Data<Metrics>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
+
+    let stock = db.get_stock_per_warehouse(&tenant).await?;
+    let text = metrics.render(&tenant, &db.usage.stats(), &stock);
+
+    Ok(HttpResponse::Ok().content_type("text/plain; version=0.0.4; charset=utf-8").body(text))
+}
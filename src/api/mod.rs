@@ -1 +1,17 @@
-pub mod mysqlapi;
\ No newline at end of file
+pub mod mysqlapi;
+pub mod lots;
+pub mod serials;
+pub mod products;
+pub mod labels;
+pub mod scanning;
+pub mod packaging;
+pub mod handling_units;
+pub mod stock_status;
+pub mod returns;
+pub mod valuation;
+pub mod reports;
+pub mod abc;
+pub mod health;
+pub mod users;
+pub mod metrics;
+pub mod openapi;
\ No newline at end of file
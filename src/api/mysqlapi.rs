@@ -1,26 +1,43 @@
 use crate::db::database::Database;
+use crate::models::tenant::Tenant;
 
-use actix_web::web::Data;
-use actix_web::{get, post, Responder, HttpResponse, web::Json,delete};
+use actix_web::web::{Data, Query};
+use actix_web::{get, post, HttpResponse, web::Json,delete};
 
 use crate::models::outgoing::RemoveUniqueIdentifierRequest;
-use crate::models::incoming::AddOrUpdateUniqueIdentifierRequest;
+use crate::models::incoming::{AddOrUpdateUniqueIdentifierRequest, UniqueIdentifier};
+use crate::models::empty_stock::StockListQuery;
+use crate::models::errors::{ApiError, ErrorBody};
 
 use validator::Validate;
 
-//GET / unique identifiers
+//GET / unique identifiers, ?breakdown=true adds pallets/boxes/pieces per row,
+//?include_empty=true adds rows without pcs and ?emptied_within_days=N lists recently emptied rows
+#[utoipa::path(
+    tag = "stock",
+    params(StockListQuery),
+    responses(
+        (status = 200, description = "Stock rows", body = Vec<UniqueIdentifier>),
+        (status = 404, description = "No stock rows", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
 #[get("/unique_identifiers")]
-async fn get_unique_identifiers(db: Data<Database>) -> impl Responder {
-    match db.get_all_locations().await {
-        Ok(found_locations) => {
-            if found_locations.is_empty() {
-                HttpResponse::NotFound().body("No data available in the database")
-            } else {
-                HttpResponse::Ok().json(found_locations)
-            }
-        }
-        Err(_) => HttpResponse::InternalServerError().body("Error retrieving unique identifiers"),
+async fn get_unique_identifiers(db: Data<Database>, tenant: Tenant, query: Query<StockListQuery>) -> Result<HttpResponse, ApiError> {
+
+    query.validate()?;
+
+    let mut found_locations = db.get_locations(&tenant, &query).await?;
+    if found_locations.is_empty() {
+        return Err(ApiError::NotFound("No data available in the database".to_string()));
+    }
+
+    if query.breakdown {
+        db.attach_breakdown(&tenant, &mut found_locations).await?;
     }
+
+    Ok(HttpResponse::Ok().json(found_locations))
 }
 
 // --------------- TEST 1.a --------------- //
@@ -80,38 +97,50 @@ async fn get_unique_identifiers(db: Data<Database>) -> impl Responder {
 // }
 
 //POST /unique_identifiers
+#[utoipa::path(
+    tag = "stock",
+    responses(
+        (status = 200, description = "Stock added", body = String, content_type = "text/plain"),
+        (status = 404, description = "Unknown product", body = ErrorBody),
+        (status = 409, description = "Serial number already in stock", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
 #[post("/add_or_update_unique_identifier")]
-async fn add_or_update_unique_identifier( db: Data<Database>, body: Json<AddOrUpdateUniqueIdentifierRequest>) -> impl Responder {
+async fn add_or_update_unique_identifier( db: Data<Database>, tenant: Tenant, body: Json<AddOrUpdateUniqueIdentifierRequest>) -> Result<HttpResponse, ApiError> {
     
-    let is_valid = body.validate();
-    match is_valid {
-        Ok(_) => {
-            match db.add_or_update_unique_identifier(&body).await {
-                Ok(_) => HttpResponse::Ok().body("Identifier added or updated successfully!"),
-                Err(_) => HttpResponse::InternalServerError().body("Failed to add or update identifier"),
-            }
-        }
-        Err(_) => HttpResponse::BadRequest().body("Invalid input. Please provide valid identifier details."),
-    }
+    body.validate()?;
+
+    db.add_or_update_unique_identifier(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Identifier added or updated successfully!"))
 }
 
-//UPDATE or DELETE unique identifiers
+//UPDATE or DELETE unique identifiers, 409 when there are not enough pcs for removal
+#[utoipa::path(
+    tag = "stock",
+    responses(
+        (status = 200, description = "Stock removed", body = String, content_type = "text/plain"),
+        (status = 404, description = "Unknown unique identifier or product", body = ErrorBody),
+        (status = 409, description = "Not enough pcs", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
 #[delete("/remove_unique_identifiers")]
-async fn remove_unique_identifier(body: Json<RemoveUniqueIdentifierRequest>, db: Data<Database>) -> impl Responder {
+async fn remove_unique_identifier(body: Json<RemoveUniqueIdentifierRequest>, db: Data<Database>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
     
-    let is_valid = body.validate();
-
-    match is_valid {
-        Ok(_) => {
-        match db.remove_unique_identifier(&body).await{
-            Ok(_)=> HttpResponse::Ok().body("Identifier updated or removed succefully!"),
-            Err(_)=> HttpResponse::InternalServerError().body("Faile to update or remove identifier. Posible reason: Not enough quantity for removal."),
-            }
+    body.validate()?;
+
+    match db.remove_unique_identifier(&tenant, &body).await {
+        Ok(_) => {}
+        Err(error @ ApiError::InsufficientStock(_)) => {
+            db.metrics.record_insufficient_stock(&tenant, &body.warehouse);
+            return Err(error);
         }
-        Err(_)=> HttpResponse::BadRequest().body("Invalid input. Please provide valid identifier details.")
+        Err(error) => return Err(error),
     }
-}
-
-
-
 
+    Ok(HttpResponse::Ok().body("Identifier updated or removed succefully!"))
+}
@@ -0,0 +1,110 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::auth::middleware::API_KEY_HEADER;
+
+// OpenAPI 3 document of every route, built from the #[utoipa::path] attributes of the handlers and
+// the ToSchema models. The paths are added by the utoipa-actix-web app in main.rs as the handlers
+// are registered, so routes of disabled modules are left out. Served at /openapi.json with Swagger UI at /swagger-ui/
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Warehouse API", description = "Stock, products, labels, returns and users of the warehouse management backend."),
+    modifiers(&Credentials),
+)]
+pub struct ApiDoc;
+
+// Every operation needs a JWT or an API key unless it declares security(()), see auth::middleware
+struct Credentials;
+
+impl Modify for Credentials {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()));
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))));
+
+        openapi.security = Some(vec![
+            SecurityRequirement::new("bearer", Vec::<String>::new()),
+            SecurityRequirement::new("api_key", Vec::<String>::new()),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use utoipa_actix_web::AppExt;
+
+    use crate::config::ModuleToggles;
+
+    //document served by the app with the given modules, collected from the registered routes
+    fn served_spec(modules: &ModuleToggles) -> utoipa::openapi::OpenApi {
+        let (_, openapi) = App::new()
+            .into_utoipa_app()
+            .openapi(ApiDoc::openapi())
+            .configure(|cfg| crate::configure_routes(cfg, modules))
+            .split_for_parts();
+
+        openapi
+    }
+
+    fn operations(openapi: &utoipa::openapi::OpenApi) -> usize {
+        openapi.paths.paths.values()
+            .map(|item| [&item.get, &item.post, &item.put, &item.patch, &item.delete].iter().filter(|operation| operation.is_some()).count())
+            .sum()
+    }
+
+    #[test]
+    fn every_route_is_in_the_spec() {
+        // Registering a handler without #[utoipa::path] does not compile, this checks the routes are all collected
+        let openapi = served_spec(&ModuleToggles::default());
+
+        assert!(operations(&openapi) > 40, "expected the routes of the API, found {}", operations(&openapi));
+        for path in ["/healthz", "/auth/login", "/add_or_update_unique_identifier", "/handling_units/move", "/valuation"] {
+            assert!(openapi.paths.paths.contains_key(path), "{} is missing from the spec", path);
+        }
+    }
+
+    #[test]
+    fn disabled_modules_are_left_out_of_the_spec() {
+        let modules = ModuleToggles { handling_units: false, ..ModuleToggles::default() };
+        let openapi = served_spec(&modules);
+
+        assert!(!openapi.paths.paths.keys().any(|path| path.starts_with("/handling_units")));
+        assert!(operations(&openapi) < operations(&served_spec(&ModuleToggles::default())));
+    }
+
+    #[actix_web::test]
+    async fn spec_and_swagger_ui_are_served() {
+        use actix_web::test;
+        use utoipa_swagger_ui::SwaggerUi;
+
+        let app = test::init_service(
+            App::new().service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", served_spec(&ModuleToggles::default()))),
+        ).await;
+
+        let spec: serde_json::Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/openapi.json").to_request()).await;
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"]["/unique_identifiers"].is_object());
+
+        let page = test::call_service(&app, test::TestRequest::get().uri("/swagger-ui/").to_request()).await;
+        assert_eq!(page.status(), 200);
+    }
+
+    #[test]
+    fn request_bodies_and_parameters_are_documented() {
+        let openapi = served_spec(&ModuleToggles::default());
+        let operation = |path: &str| openapi.paths.paths.get(path).unwrap();
+
+        let add = operation("/add_or_update_unique_identifier").post.as_ref().unwrap();
+        assert!(add.request_body.is_some());
+
+        let access = operation("/users/{username}/access").put.as_ref().unwrap();
+        assert!(access.parameters.iter().flatten().any(|parameter| parameter.name == "username"));
+
+        let schemas = &openapi.components.as_ref().unwrap().schemas;
+        for schema in ["AddOrUpdateUniqueIdentifierRequest", "RemoveUniqueIdentifierRequest", "UniqueIdentifier", "ErrorBody"] {
+            assert!(schemas.contains_key(schema), "{} is missing from the components", schema);
+        }
+    }
+}
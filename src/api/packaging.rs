@@ -0,0 +1,53 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::packaging::{GetPackaging, SetPackagingRequest, PackagingDefinition};
+
+use validator::Validate;
+
+//GET / packaging definitions of a product
+#[utoipa::path(
+    tag = "packaging",
+    params(GetPackaging),
+    responses(
+        (status = 200, description = "Packaging definitions of the product", body = Vec<PackagingDefinition>),
+        (status = 404, description = "No packaging defined", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/products/{product_code}/packaging")]
+async fn get_packaging(db: Data<Database>, tenant: Tenant, product: Path<GetPackaging>) -> Result<HttpResponse, ApiError> {
+
+    product.validate()?;
+
+    let definitions = db.get_packaging(&tenant, &product).await?;
+    if definitions.is_empty() {
+        return Err(ApiError::NotFound("No packaging defined for the specified product.".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(definitions))
+}
+
+//POST / define packaging of a product, e.g. 1 box = 12 pcs, 1 pallet = 40 boxes
+#[utoipa::path(
+    tag = "packaging",
+    responses(
+        (status = 200, description = "Packaging added or updated", body = String, content_type = "text/plain"),
+        (status = 422, description = "Validation failed, or a box without pcs or a pallet without boxes or pcs", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/products/packaging")]
+async fn set_packaging(db: Data<Database>, tenant: Tenant, body: Json<SetPackagingRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.set_packaging(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Packaging added or updated successfully!"))
+}
@@ -0,0 +1,95 @@
+use crate::config::ProductsConfig;
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::products::{AddProductRequest, CreateProductFamilyRequest, GetProductFamily, SetProductAttributesRequest, ProductFamily, ProductFamilyDetails, Products};
+
+use validator::Validate;
+
+//POST / create product, product_code is generated in the company prefix when omitted
+#[utoipa::path(
+    tag = "products",
+    responses(
+        (status = 201, description = "Created product", body = Products),
+        (status = 404, description = "Unknown product family", body = ErrorBody),
+        (status = 409, description = "Product code or variant already exists", body = ErrorBody),
+        (status = 422, description = "Validation failed or no company prefix to generate the product_code", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/products")]
+async fn create_product(db: Data<Database>, products: Data<ProductsConfig>, tenant: Tenant, body: Json<AddProductRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let product = db.create_product(&tenant, &body, &products).await?;
+
+    Ok(HttpResponse::Created().json(product))
+}
+
+//POST / set attributes of a product, attributes that are not given are kept
+#[utoipa::path(
+    tag = "products",
+    responses(
+        (status = 200, description = "Product with its new attributes", body = Products),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 409, description = "A variant with the same attributes already exists", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/products/attributes")]
+async fn set_product_attributes(db: Data<Database>, tenant: Tenant, body: Json<SetProductAttributesRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let product = db.set_product_attributes(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().json(product))
+}
+
+//POST / create product family
+#[utoipa::path(
+    tag = "products",
+    responses(
+        (status = 201, description = "Created product family", body = ProductFamily),
+        (status = 409, description = "Family code already exists", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/product_families")]
+async fn create_product_family(db: Data<Database>, tenant: Tenant, body: Json<CreateProductFamilyRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let family = db.create_product_family(&tenant, &body).await?;
+
+    Ok(HttpResponse::Created().json(family))
+}
+
+//GET / product family with all its variants
+#[utoipa::path(
+    tag = "products",
+    params(GetProductFamily),
+    responses(
+        (status = 200, description = "Product family with its variants", body = ProductFamilyDetails),
+        (status = 404, description = "Product family not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/product_families/{family_code}")]
+async fn get_product_family(db: Data<Database>, tenant: Tenant, family: Path<GetProductFamily>) -> Result<HttpResponse, ApiError> {
+
+    family.validate()?;
+
+    let details = db.get_product_family(&tenant, &family.family_code).await?
+        .ok_or_else(|| ApiError::NotFound("Product family not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(details))
+}
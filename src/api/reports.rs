@@ -0,0 +1,39 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Query};
+use actix_web::{get, HttpResponse};
+
+use crate::models::reports::{slow_moving_csv, ReportFormat, SlowMovingQuery, SlowMovingStock};
+
+use validator::Validate;
+
+//GET / stock rows without outbound movement in N days, as json or csv
+#[utoipa::path(
+    tag = "reports",
+    params(SlowMovingQuery),
+    responses(
+        (status = 200, description = "Stock rows without outbound movement", content(
+            (Vec<SlowMovingStock> = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/reports/slow_moving")]
+async fn get_slow_moving_stock(db: Data<Database>, tenant: Tenant, query: Query<SlowMovingQuery>) -> Result<HttpResponse, ApiError> {
+
+    query.validate()?;
+
+    let rows = db.get_slow_moving_stock(&tenant, &query).await?;
+
+    Ok(match query.format {
+        ReportFormat::Json => HttpResponse::Ok().json(rows),
+        ReportFormat::Csv => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(("Content-Disposition", "attachment; filename=\"slow_moving.csv\""))
+            .body(slow_moving_csv(&rows)),
+    })
+}
@@ -0,0 +1,93 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::returns::{CreateReturnAuthorizationRequest, DispositionRequest, GetReturnAuthorization, ReceiveReturnRequest, ReturnAuthorizationDetails};
+
+use validator::Validate;
+
+//POST / create return authorization (RMA) for a sales order
+#[utoipa::path(
+    tag = "returns",
+    responses(
+        (status = 201, description = "Return authorization created", body = String, content_type = "text/plain"),
+        (status = 409, description = "RMA number already exists", body = ErrorBody),
+        (status = 422, description = "Validation failed or unknown product", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/returns")]
+async fn create_return_authorization(db: Data<Database>, tenant: Tenant, body: Json<CreateReturnAuthorizationRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.create_return_authorization(&tenant, &body).await?;
+
+    Ok(HttpResponse::Created().body("Return authorization created successfully!"))
+}
+
+//GET / return authorization with lines, receipts and status
+#[utoipa::path(
+    tag = "returns",
+    params(GetReturnAuthorization),
+    responses(
+        (status = 200, description = "Return authorization with lines, receipts and status", body = ReturnAuthorizationDetails),
+        (status = 404, description = "Return authorization not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/returns/{rma_number}")]
+async fn get_return_authorization(db: Data<Database>, tenant: Tenant, rma_number: Path<GetReturnAuthorization>) -> Result<HttpResponse, ApiError> {
+
+    rma_number.validate()?;
+
+    let details = db.get_return_authorization(&tenant, &rma_number).await?
+        .ok_or_else(|| ApiError::NotFound("Return authorization not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(details))
+}
+
+//POST / receive returned goods with condition and reason
+#[utoipa::path(
+    tag = "returns",
+    responses(
+        (status = 201, description = "Id of the receipt", body = Object, example = json!({"receipt_id": 17})),
+        (status = 404, description = "Product is not authorized for return on the RMA", body = ErrorBody),
+        (status = 422, description = "Validation failed or more pcs than authorized", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/returns/receive")]
+async fn receive_return(db: Data<Database>, tenant: Tenant, body: Json<ReceiveReturnRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let receipt_id = db.receive_return(&tenant, &body).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({ "receipt_id": receipt_id })))
+}
+
+//POST / disposition of received return: restock, quarantine or scrap
+#[utoipa::path(
+    tag = "returns",
+    responses(
+        (status = 200, description = "Disposition posted", body = String, content_type = "text/plain"),
+        (status = 404, description = "Return receipt not found", body = ErrorBody),
+        (status = 409, description = "Disposition was already posted", body = ErrorBody),
+        (status = 422, description = "Validation failed, or no warehouse and location to restock or quarantine to", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/returns/disposition")]
+async fn dispose_return(db: Data<Database>, tenant: Tenant, body: Json<DispositionRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.dispose_return(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Disposition posted successfully!"))
+}
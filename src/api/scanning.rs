@@ -0,0 +1,36 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::Data;
+use actix_web::{post, HttpResponse, web::Json};
+
+use crate::models::gs1::{parse_gs1, ScanRequest, ScanResult};
+
+use validator::Validate;
+
+//POST / parse raw GS1-128 scanner input into structured fields and prefilled requests
+#[utoipa::path(
+    tag = "scanning",
+    responses(
+        (status = 200, description = "Parsed scan with prefilled requests", body = ScanResult),
+        (status = 400, description = "Input is not valid GS1-128", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/scan/parse")]
+async fn parse_scan(db: Data<Database>, tenant: Tenant, body: Json<ScanRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let scan = parse_gs1(&body.raw)?;
+
+    let product = match &scan.gtin {
+        Some(gtin) => db.get_product_by_gtin(&tenant, gtin).await?,
+        None => None,
+    };
+
+    let body = body.into_inner();
+    Ok(HttpResponse::Ok().json(ScanResult::new(scan, product, body.warehouse, body.location)))
+}
@@ -0,0 +1,54 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::serials::{GetSerialNumber, SetSerialTracking, SerialNumberDetails};
+
+use validator::Validate;
+
+//GET / current location and movement history of a serial number
+#[utoipa::path(
+    tag = "serials",
+    params(GetSerialNumber),
+    responses(
+        (status = 200, description = "Serial number with its movements", body = SerialNumberDetails),
+        (status = 404, description = "Serial number not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/serial_numbers/{serial_number}")]
+async fn get_serial_number(db: Data<Database>, tenant: Tenant, serial_number: Path<GetSerialNumber>) -> Result<HttpResponse, ApiError> {
+
+    serial_number.validate()?;
+
+    let details = db.get_serial_number_details(&tenant, &serial_number).await?
+        .ok_or_else(|| ApiError::NotFound("Serial number not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(details))
+}
+
+//POST / turn serial tracking on or off for a product
+#[utoipa::path(
+    tag = "serials",
+    responses(
+        (status = 200, description = "Serial tracking updated", body = String, content_type = "text/plain"),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/products/serial_tracking")]
+async fn set_serial_tracking(db: Data<Database>, tenant: Tenant, body: Json<SetSerialTracking>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    if !db.set_serial_tracking(&tenant, &body).await? {
+        return Err(ApiError::NotFound("Product not found".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().body("Serial tracking updated successfully!"))
+}
@@ -0,0 +1,51 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::Data;
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::stock_status::{ChangeStockStatusRequest, AvailableStock};
+
+use validator::Validate;
+
+//POST / move a quantity to another stock status (available, quarantine, blocked, damaged)
+#[utoipa::path(
+    tag = "stock",
+    responses(
+        (status = 200, description = "Stock status changed", body = String, content_type = "text/plain"),
+        (status = 404, description = "Unknown stock row or product", body = ErrorBody),
+        (status = 409, description = "Not enough loose pcs with the current status", body = ErrorBody),
+        (status = 422, description = "Validation failed, or the stock already has the status", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/unique_identifiers/status")]
+async fn change_stock_status(db: Data<Database>, tenant: Tenant, body: Json<ChangeStockStatusRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    db.change_stock_status(&tenant, &body).await?;
+
+    Ok(HttpResponse::Ok().body("Stock status changed successfully!"))
+}
+
+//GET / available quantity per product
+#[utoipa::path(
+    tag = "stock",
+    responses(
+        (status = 200, description = "Available pcs per product", body = Vec<AvailableStock>),
+        (status = 404, description = "No available stock", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/available_stock")]
+async fn get_available_stock(db: Data<Database>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
+
+    let available = db.get_available_stock(&tenant).await?;
+    if available.is_empty() {
+        return Err(ApiError::NotFound("No available stock in the database".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(available))
+}
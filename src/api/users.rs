@@ -0,0 +1,211 @@
+use crate::auth::credentials::{generate_api_key, hash_password, verify_password, JwtKeys};
+use crate::auth::Principal;
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{self, Data, Path};
+use actix_web::{get, post, put, delete, HttpResponse, web::Json};
+
+use crate::models::users::{ChangePasswordRequest, CreateApiKeyRequest, CreateUserRequest, CreatedApiKey, GetApiKey, GetUser, LoginRequest, LoginResponse, SetUserAccessRequest, ApiKey, User};
+
+use validator::Validate;
+
+// Argon2 takes tens of milliseconds, so it runs on the blocking thread pool
+async fn hash_on_blocking_pool(password: String) -> Result<String, ApiError> {
+    web::block(move || hash_password(&password))
+        .await
+        .map_err(|err| ApiError::Internal(err.to_string()))?
+        .map_err(ApiError::Internal)
+}
+
+//POST / login with username and password, the tenant is taken from the X-Tenant-Id header
+#[utoipa::path(
+    tag = "users",
+    params(("X-Tenant-Id" = String, Header, description = "Tenant of the user")),
+    security(()),
+    responses(
+        (status = 200, description = "Issued token", body = LoginResponse),
+        (status = 401, description = "Invalid username or password", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+)]
+#[post("/auth/login")]
+async fn login(db: Data<Database>, keys: Data<JwtKeys>, tenant: Tenant, body: Json<LoginRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let invalid = || ApiError::Unauthorized("Invalid username or password.".to_string());
+    let hash = db.get_password_hash(&tenant, &body.username).await?.ok_or_else(invalid)?;
+
+    let password = body.password.clone();
+    let valid = web::block(move || verify_password(&password, &hash))
+        .await
+        .map_err(|err| ApiError::Internal(err.to_string()))?;
+    if !valid {
+        return Err(invalid());
+    }
+
+    let (token, expires_at) = keys.issue(tenant.id(), &body.username).map_err(ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(LoginResponse { token, token_type: "Bearer", expires_at }))
+}
+
+//GET / users of the tenant
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 200, description = "Users of the tenant", body = Vec<User>),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/users")]
+async fn get_users(db: Data<Database>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
+
+    let users = db.get_users(&tenant).await?;
+
+    Ok(HttpResponse::Ok().json(users))
+}
+
+//POST / create user with a password
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 201, description = "Created user without password", body = Object),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/users")]
+async fn create_user(db: Data<Database>, tenant: Tenant, body: Json<CreateUserRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let password_hash = hash_on_blocking_pool(body.password.clone()).await?;
+    db.create_user(&tenant, &body.username, &password_hash, body.role, &body.access).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({ "username": body.username, "role": body.role, "all_warehouses": body.access.all_warehouses, "warehouses": body.access.warehouses })))
+}
+
+//PUT / set role and warehouses of a user, applies from the user's next request
+#[utoipa::path(
+    tag = "users",
+    params(GetUser),
+    responses(
+        (status = 204, description = "Role and warehouses set"),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[put("/users/{username}/access")]
+async fn set_user_access(db: Data<Database>, tenant: Tenant, user: Path<GetUser>, body: Json<SetUserAccessRequest>) -> Result<HttpResponse, ApiError> {
+
+    user.validate()?;
+    body.validate()?;
+
+    db.set_user_access(&tenant, &user.username, body.role, &body.access).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+//PUT / set a new password, tokens issued before stay valid until they expire
+#[utoipa::path(
+    tag = "users",
+    params(GetUser),
+    responses(
+        (status = 204, description = "Password set"),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[put("/users/{username}/password")]
+async fn change_user_password(db: Data<Database>, tenant: Tenant, user: Path<GetUser>, body: Json<ChangePasswordRequest>) -> Result<HttpResponse, ApiError> {
+
+    user.validate()?;
+    body.validate()?;
+
+    let password_hash = hash_on_blocking_pool(body.password.clone()).await?;
+    db.set_user_password(&tenant, &user.username, &password_hash).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+//DELETE / delete user, its tokens are rejected from the next request on
+#[utoipa::path(
+    tag = "users",
+    params(GetUser),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[delete("/users/{username}")]
+async fn delete_user(db: Data<Database>, tenant: Tenant, user: Path<GetUser>) -> Result<HttpResponse, ApiError> {
+
+    user.validate()?;
+
+    db.delete_user(&tenant, &user.username).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+//GET / API keys of the tenant, without their secrets
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 200, description = "API keys of the tenant without their secrets", body = Vec<ApiKey>),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/api_keys")]
+async fn get_api_keys(db: Data<Database>, tenant: Tenant) -> Result<HttpResponse, ApiError> {
+
+    let keys = db.get_api_keys(&tenant).await?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+//POST / create API key for a machine integration, the key is only returned here
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 201, description = "Created API key with its secret, only returned here", body = CreatedApiKey),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/api_keys")]
+async fn create_api_key(db: Data<Database>, principal: Principal, body: Json<CreateApiKeyRequest>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    let (key_id, key, key_hash) = generate_api_key();
+    db.create_api_key(&principal.tenant, &key_id, &key_hash, &body, &principal.name()).await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiKey { key_id, name: body.name.clone(), role: body.role, access: body.access.clone(), key }))
+}
+
+//DELETE / revoke API key
+#[utoipa::path(
+    tag = "users",
+    params(GetApiKey),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 404, description = "API key not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[delete("/api_keys/{key_id}")]
+async fn revoke_api_key(db: Data<Database>, tenant: Tenant, api_key: Path<GetApiKey>) -> Result<HttpResponse, ApiError> {
+
+    api_key.validate()?;
+
+    db.revoke_api_key(&tenant, &api_key.key_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,72 @@
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::errors::{ApiError, ErrorBody};
+
+use actix_web::web::{Data, Path, Query};
+use actix_web::{get, post, HttpResponse, web::Json};
+
+use crate::models::valuation::{CostLayersQuery, GetCostLayers, SetCostMethod, ValuationQuery, CostLayer, ValuationReport};
+
+use validator::Validate;
+
+//POST / set FIFO or weighted-average cost method for a product
+#[utoipa::path(
+    tag = "valuation",
+    responses(
+        (status = 200, description = "Cost method updated", body = String, content_type = "text/plain"),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[post("/products/cost_method")]
+async fn set_cost_method(db: Data<Database>, tenant: Tenant, body: Json<SetCostMethod>) -> Result<HttpResponse, ApiError> {
+
+    body.validate()?;
+
+    if !db.set_cost_method(&tenant, &body).await? {
+        return Err(ApiError::NotFound("Product not found".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().body("Cost method updated successfully!"))
+}
+
+//GET / remaining cost layers of a product
+#[utoipa::path(
+    tag = "valuation",
+    params(GetCostLayers, CostLayersQuery),
+    responses(
+        (status = 200, description = "Remaining cost layers", body = Vec<CostLayer>),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/products/{product_code}/cost_layers")]
+async fn get_cost_layers(db: Data<Database>, tenant: Tenant, product_code: Path<GetCostLayers>, query: Query<CostLayersQuery>) -> Result<HttpResponse, ApiError> {
+
+    product_code.validate().and(query.validate())?;
+
+    let layers = db.get_cost_layers(&tenant, &product_code, &query).await?;
+
+    Ok(HttpResponse::Ok().json(layers))
+}
+
+//GET / stock valuation per product and warehouse at a date
+#[utoipa::path(
+    tag = "valuation",
+    params(ValuationQuery),
+    responses(
+        (status = 200, description = "Stock valuation per product and warehouse", body = ValuationReport),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    ),
+)]
+#[get("/valuation")]
+async fn get_valuation(db: Data<Database>, tenant: Tenant, query: Query<ValuationQuery>) -> Result<HttpResponse, ApiError> {
+
+    query.validate()?;
+
+    let report = db.get_valuation(&tenant, &query).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
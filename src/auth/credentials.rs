@@ -0,0 +1,126 @@
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::AuthConfig;
+
+// API keys look like wms_<key_id>_<secret>, only the key id and a hash of the secret are stored
+const API_KEY_PREFIX: &str = "wms_";
+
+//argon2 hash in PHC format, salt included
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| err.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+//new API key as (key_id, full key, hash of the secret), the full key is only shown once
+pub fn generate_api_key() -> (String, String, String) {
+    let key_id = random_hex(8);
+    let secret = random_hex(32);
+    let key = format!("{}{}_{}", API_KEY_PREFIX, key_id, secret);
+    (key_id, key, hash_api_key_secret(&secret))
+}
+
+//key_id and secret of a key sent in X-Api-Key
+pub fn parse_api_key(key: &str) -> Option<(&str, &str)> {
+    let (key_id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    (key_id.len() == 16 && !secret.is_empty()).then_some((key_id, secret))
+}
+
+// Secrets are random, so a plain SHA-256 is enough to keep them out of the database
+pub fn hash_api_key_secret(secret: &str) -> String {
+    to_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buffer);
+    to_hex(&buffer)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+//JWT claims of a logged in user
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Claims{
+    pub sub: String,
+    pub tenant: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+//signs and checks the JWTs issued by /auth/login (HS256)
+pub struct JwtKeys{
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl: Duration,
+}
+
+impl JwtKeys {
+    pub fn new(config: &AuthConfig) -> JwtKeys {
+        JwtKeys {
+            encoding: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+            decoding: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+            ttl: Duration::minutes(config.token_ttl_minutes),
+        }
+    }
+
+    pub fn issue(&self, tenant: &str, username: &str) -> Result<(String, DateTime<Utc>), String> {
+        let now = Utc::now();
+        let expires_at = now + self.ttl;
+        let claims = Claims { sub: username.to_string(), tenant: tenant.to_string(), iat: now.timestamp(), exp: expires_at.timestamp() };
+
+        encode(&Header::default(), &claims, &self.encoding)
+            .map(|token| (token, expires_at))
+            .map_err(|err| err.to_string())
+    }
+
+    //signature and expiry are checked
+    pub fn verify(&self, token: &str) -> Option<Claims> {
+        decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map(|data| data.claims)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords_keys_and_tokens_round_trip() {
+        let hash = hash_password("correct horse battery").unwrap();
+        assert!(verify_password("correct horse battery", &hash));
+        assert!(!verify_password("wrong horse battery", &hash));
+
+        let (key_id, key, key_hash) = generate_api_key();
+        let (parsed_id, secret) = parse_api_key(&key).unwrap();
+        assert_eq!(parsed_id, key_id);
+        assert_eq!(hash_api_key_secret(secret), key_hash);
+        assert!(parse_api_key("wms_short_secret").is_none());
+
+        let config = AuthConfig { jwt_secret: "a-long-enough-secret-for-signing-jwts".to_string(), token_ttl_minutes: 60 };
+        let keys = JwtKeys::new(&config);
+        let (token, _) = keys.issue("default", "marko").unwrap();
+        let claims = keys.verify(&token).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.tenant.as_str()), ("marko", "default"));
+
+        let other = JwtKeys::new(&AuthConfig { jwt_secret: "another-secret-that-is-long-enough!!".to_string(), token_ttl_minutes: 60 });
+        assert!(other.verify(&token).is_none());
+    }
+}
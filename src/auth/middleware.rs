@@ -0,0 +1,262 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::{Bytes, Data};
+use actix_web::{Error, HttpMessage, ResponseError};
+
+use crate::auth::credentials::{hash_api_key_secret, parse_api_key, JwtKeys};
+use crate::auth::policy::{named_references, named_warehouses, rule_for};
+use crate::auth::{Principal, Subject};
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::tenant::Tenant;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+// Reachable without credentials: probes, the login itself and the API documentation
+const PUBLIC_PATHS: &[&str] = &["/healthz", "/readyz", "/auth/login", "/openapi.json"];
+
+// Swagger UI page and its assets
+const SWAGGER_UI_PATH: &str = "/swagger-ui";
+
+// Every other request needs a JWT (Authorization: Bearer <token>) or an API key (X-Api-Key) and
+// a role that is allowed to use the route, see auth::policy. The principal is stored in the
+// request extensions and read by the Principal and Tenant extractors
+pub struct Authentication;
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            if PUBLIC_PATHS.contains(&req.path()) || req.path().starts_with(SWAGGER_UI_PATH) {
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
+            }
+
+            let checked = match authenticate(&req).await {
+                Ok(principal) => authorize(&mut req, &principal).await.map(|_| principal),
+                Err(error) => Err(error),
+            };
+
+            match checked {
+                Ok(principal) => {
+                    let span = tracing::Span::current();
+                    span.record("tenant", principal.tenant.id());
+                    span.record("user", principal.name().as_str());
+                    req.extensions_mut().insert(principal);
+                    service.call(req).await.map(ServiceResponse::map_into_left_body)
+                }
+                Err(error) => Ok(req.into_response(error.error_response()).map_into_right_body()),
+            }
+        })
+    }
+}
+
+async fn authenticate(req: &ServiceRequest) -> Result<Principal, ApiError> {
+    let db = req.app_data::<Data<Database>>().expect("Database is registered as app data");
+    let keys = req.app_data::<Data<JwtKeys>>().expect("JwtKeys are registered as app data");
+
+    let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+
+    let bearer = header("Authorization").and_then(|value| value.strip_prefix("Bearer ")).map(str::trim);
+
+    // Scrapers such as Prometheus can only send a bearer token, so API keys are accepted there too
+    if let Some(token) = bearer.filter(|token| parse_api_key(token).is_none()) {
+        let claims = keys.verify(token).ok_or_else(|| unauthorized("Invalid or expired token."))?;
+        let tenant = Tenant::new(&claims.tenant).ok_or_else(|| unauthorized("Invalid or expired token."))?;
+
+        // Deleted users lose access and changed roles apply right away, not only when the token expires
+        let (role, access) = db.get_user_access(&tenant, &claims.sub).await?.ok_or_else(|| unauthorized("User no longer exists."))?;
+
+        return Ok(Principal { tenant, subject: Subject::User(claims.sub), role, access });
+    }
+
+    if let Some(key) = header(API_KEY_HEADER).or(bearer) {
+        let (key_id, secret) = parse_api_key(key.trim()).ok_or_else(|| unauthorized("Invalid API key."))?;
+        let (tenant_id, key_hash, role, access) = db.find_api_key(key_id).await?.ok_or_else(|| unauthorized("Invalid API key."))?;
+        let tenant = Tenant::new(&tenant_id).ok_or_else(|| unauthorized("Invalid API key."))?;
+
+        if hash_api_key_secret(secret) != key_hash {
+            return Err(unauthorized("Invalid API key."));
+        }
+
+        return Ok(Principal { tenant, subject: Subject::ApiKey(key_id.to_string()), role, access });
+    }
+
+    Err(unauthorized("Authentication required, send Authorization: Bearer <token> or X-Api-Key."))
+}
+
+async fn authorize(req: &mut ServiceRequest, principal: &Principal) -> Result<(), ApiError> {
+    let pattern = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+    let rule = rule_for(req.method(), &pattern);
+
+    if principal.role < rule.role {
+        return Err(ApiError::Forbidden(format!("{} rights are required, you are signed in as {}.", rule.role.as_str(), principal.role.as_str())));
+    }
+
+    if !rule.warehouse_scoped || principal.can_access_all_warehouses() {
+        return Ok(());
+    }
+
+    // The body is read here to find the warehouses and put back for the handler. Bodies that are
+    // not JSON name no warehouse and are rejected by the handler itself
+    let body = req.extract::<Bytes>().await.map_err(|err| ApiError::Internal(err.to_string()))?;
+    let json: Option<serde_json::Value> = serde_json::from_slice(&body).ok();
+
+    let (_, mut payload) = actix_http::h1::Payload::create(true);
+    payload.unread_data(body);
+    req.set_payload(payload.into());
+
+    let Some(json) = json else {
+        return Ok(());
+    };
+
+    // Handling units and receipts are checked where they are now, e.g. a move needs both the
+    // warehouse it leaves and the one named as destination
+    let mut warehouses = named_warehouses(&json);
+    let references = named_references(&json);
+    if !references.is_empty() {
+        let db = req.app_data::<Data<Database>>().expect("Database is registered as app data");
+        warehouses.extend(db.handling_unit_warehouses(&principal.tenant, &references.lpns).await?);
+        warehouses.extend(db.receipt_warehouses(&principal.tenant, &references.receipt_ids).await?);
+    }
+
+    check_warehouses(principal, &warehouses)
+}
+
+fn check_warehouses(principal: &Principal, warehouses: &[String]) -> Result<(), ApiError> {
+    match warehouses.iter().find(|warehouse| !principal.can_access_warehouse(warehouse)) {
+        Some(warehouse) => Err(ApiError::Forbidden(format!("You are not assigned to warehouse {}.", warehouse))),
+        None => Ok(()),
+    }
+}
+
+fn unauthorized(message: &str) -> ApiError {
+    ApiError::Unauthorized(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+    use crate::config::{AuthConfig, DatabaseConfig};
+    use crate::models::users::{Role, WarehouseAccess};
+
+    #[actix_web::test]
+    async fn requests_without_valid_credentials_are_rejected() {
+        // The pool connects lazily, none of these requests reaches the database
+        let db = Database::with_pool(mysql_async::Pool::new("mysql://localhost/wms"), &DatabaseConfig::default());
+        let keys = JwtKeys::new(&AuthConfig { jwt_secret: "a-long-enough-secret-for-signing-jwts".to_string(), token_ttl_minutes: 60 });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(db))
+                .app_data(Data::new(keys))
+                .wrap(Authentication)
+                .route("/healthz", web::get().to(HttpResponse::Ok))
+                .route("/unique_identifiers", web::get().to(HttpResponse::Ok)),
+        ).await;
+
+        let probe = test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(probe.status(), 200);
+
+        let anonymous = test::call_service(&app, test::TestRequest::get().uri("/unique_identifiers").to_request()).await;
+        assert_eq!(anonymous.status(), 401);
+
+        let forged = test::TestRequest::get().uri("/unique_identifiers").insert_header(("Authorization", "Bearer not.a.token")).to_request();
+        assert_eq!(test::call_service(&app, forged).await.status(), 401);
+
+        let malformed_key = test::TestRequest::get().uri("/unique_identifiers").insert_header((API_KEY_HEADER, "secret")).to_request();
+        assert_eq!(test::call_service(&app, malformed_key).await.status(), 401);
+    }
+
+    fn operator(warehouses: &[&str]) -> Principal {
+        Principal {
+            tenant: Tenant::new("default").unwrap(),
+            subject: Subject::User("marko".to_string()),
+            role: Role::Operator,
+            access: WarehouseAccess { all_warehouses: false, warehouses: warehouses.iter().map(|warehouse| warehouse.to_string()).collect() },
+        }
+    }
+
+    #[actix_web::test]
+    async fn operators_only_change_stock_in_their_warehouses() {
+        let operator = operator(&["HALA 5"]);
+        let removal = |warehouse: &str| test::TestRequest::delete()
+            .uri("/remove_unique_identifiers")
+            .set_payload(format!(r#"{{"warehouse": "{}", "location": "A-01", "pcs": 1}}"#, warehouse))
+            .to_srv_request();
+
+        let mut own = removal("HALA 5");
+        assert!(authorize(&mut own, &operator).await.is_ok());
+        let body = own.extract::<Bytes>().await.unwrap();
+        assert!(body.starts_with(br#"{"warehouse": "HALA 5""#));
+
+        let mut other = removal("HALA 1");
+        assert!(matches!(authorize(&mut other, &operator).await, Err(ApiError::Forbidden(_))));
+
+        let mut catalog = test::TestRequest::post().uri("/products").to_srv_request();
+        assert!(matches!(authorize(&mut catalog, &operator).await, Err(ApiError::Forbidden(_))));
+    }
+
+    #[actix_web::test]
+    async fn operators_without_warehouses_change_no_stock() {
+        let unassigned = operator(&[]);
+        let mut removal = test::TestRequest::delete()
+            .uri("/remove_unique_identifiers")
+            .set_payload(r#"{"warehouse": "HALA 5", "location": "A-01", "pcs": 1}"#)
+            .to_srv_request();
+        assert!(matches!(authorize(&mut removal, &unassigned).await, Err(ApiError::Forbidden(_))));
+
+        let everywhere = Principal { access: WarehouseAccess::all(), ..unassigned };
+        let mut removal = test::TestRequest::delete()
+            .uri("/remove_unique_identifiers")
+            .set_payload(r#"{"warehouse": "HALA 5", "location": "A-01", "pcs": 1}"#)
+            .to_srv_request();
+        assert!(authorize(&mut removal, &everywhere).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn handling_units_are_not_moved_out_of_foreign_warehouses() {
+        let operator = operator(&["HALA 5"]);
+
+        // The destination is assigned, the warehouse the unit is stored in is not
+        let stored_in = "HALA 1".to_string();
+        let destination = "HALA 5".to_string();
+        assert!(matches!(check_warehouses(&operator, &[destination.clone(), stored_in]), Err(ApiError::Forbidden(_))));
+        assert!(check_warehouses(&operator, &[destination.clone(), destination]).is_ok());
+    }
+}
@@ -0,0 +1,74 @@
+pub mod credentials;
+pub mod middleware;
+pub mod policy;
+
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use validator::Validate;
+
+use crate::auth::credentials::hash_password;
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::tenant::Tenant;
+use crate::models::users::{CreateUserRequest, Role, WarehouseAccess};
+
+//who made the request, set by the authentication middleware
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal{
+    pub tenant: Tenant,
+    pub subject: Subject,
+    pub role: Role,
+    pub access: WarehouseAccess,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subject {
+    //warehouse staff logged in with username and password
+    User(String),
+    //machine integration, identified by the key id
+    ApiKey(String),
+}
+
+impl Principal {
+    //admins are never limited to warehouses
+    pub fn can_access_all_warehouses(&self) -> bool {
+        self.role == Role::Admin || self.access.all_warehouses
+    }
+
+    pub fn can_access_warehouse(&self, warehouse: &str) -> bool {
+        self.role == Role::Admin || self.access.allows(warehouse)
+    }
+
+    //name recorded with changes, e.g. created_by of an API key
+    pub fn name(&self) -> String {
+        match &self.subject {
+            Subject::User(username) => username.clone(),
+            Subject::ApiKey(key_id) => format!("api_key:{}", key_id),
+        }
+    }
+}
+
+impl FromRequest for Principal {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let principal = req.extensions().get::<Principal>().cloned();
+
+        ready(principal.ok_or_else(|| ApiError::Unauthorized("Authentication required.".to_string())))
+    }
+}
+
+// --create-user <tenant> <username>: creates the first admin of a tenant from the command line,
+// the password is read from WMS_USER_PASSWORD so it does not end up in the shell history
+pub async fn create_user_from_env(db: &Database, tenant: &str, username: &str) -> Result<(), String> {
+    let tenant = Tenant::new(tenant).ok_or("Tenant must be 1 to 64 letters, digits, '-' or '_'")?;
+    let password = std::env::var("WMS_USER_PASSWORD").map_err(|_| "WMS_USER_PASSWORD is not set")?;
+
+    let request = CreateUserRequest { username: username.to_string(), password, role: Role::Admin, access: WarehouseAccess::all() };
+    request.validate().map_err(|errors| errors.to_string())?;
+
+    let password_hash = hash_password(&request.password)?;
+    db.create_user(&tenant, &request.username, &password_hash, request.role, &request.access).await.map_err(|err| err.to_string())
+}
@@ -0,0 +1,147 @@
+use actix_web::http::Method;
+use serde_json::Value;
+
+use crate::models::users::Role;
+
+// What a route needs: the least role, and whether the warehouses named in the JSON body, and the
+// warehouses the handling units and return receipts it refers to are stored in, have to be among
+// the warehouses of the user. All rules are here so handlers do not check rights themselves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule{
+    pub role: Role,
+    pub warehouse_scoped: bool,
+}
+
+const fn rule(role: Role, warehouse_scoped: bool) -> Rule {
+    Rule { role, warehouse_scoped }
+}
+
+// Routes that are not listed need a viewer for GET and an admin for anything else
+const RULES: &[(&str, &str, Rule)] = &[
+    // stock movements in a warehouse
+    ("POST", "/add_or_update_unique_identifier", rule(Role::Operator, true)),
+    ("DELETE", "/remove_unique_identifiers", rule(Role::Operator, true)),
+    ("POST", "/unique_identifiers/status", rule(Role::Operator, true)),
+    ("POST", "/handling_units", rule(Role::Operator, true)),
+    ("POST", "/handling_units/move", rule(Role::Operator, true)),
+    ("POST", "/handling_units/pack", rule(Role::Operator, true)),
+    ("POST", "/handling_units/unpack", rule(Role::Operator, true)),
+    ("POST", "/handling_units/nest", rule(Role::Operator, true)),
+    ("POST", "/returns/receive", rule(Role::Operator, true)),
+    // read only, sent as POST because of the request body
+    ("POST", "/allocate_sales_order", rule(Role::Viewer, false)),
+    ("POST", "/scan/parse", rule(Role::Viewer, false)),
+    ("POST", "/labels/locations", rule(Role::Viewer, false)),
+    // warehouse management
+    ("POST", "/returns", rule(Role::Manager, false)),
+    ("POST", "/returns/disposition", rule(Role::Manager, true)),
+    ("POST", "/products/packaging", rule(Role::Manager, false)),
+    ("POST", "/products/serial_tracking", rule(Role::Manager, false)),
+    ("POST", "/products/cost_method", rule(Role::Manager, false)),
+    ("POST", "/abc_analysis", rule(Role::Manager, false)),
+    // users and API keys can only be seen by admins
+    ("GET", "/users", rule(Role::Admin, false)),
+    ("GET", "/api_keys", rule(Role::Admin, false)),
+];
+
+//rule for a route pattern such as /users/{username}
+pub fn rule_for(method: &Method, pattern: &str) -> Rule {
+    let listed = RULES.iter().find(|(rule_method, rule_pattern, _)| *rule_method == method.as_str() && *rule_pattern == pattern);
+
+    match listed {
+        Some((_, _, rule)) => *rule,
+        None if *method == Method::GET => rule(Role::Viewer, false),
+        None => rule(Role::Admin, false),
+    }
+}
+
+//warehouses named anywhere in a JSON body: "warehouse" and fields ending in "_warehouse"
+pub fn named_warehouses(body: &Value) -> Vec<String> {
+    let mut warehouses = Vec::new();
+    collect_warehouses(body, &mut warehouses);
+    warehouses
+}
+
+fn collect_warehouses(value: &Value, warehouses: &mut Vec<String>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                match field {
+                    Value::String(warehouse) if name == "warehouse" || name.ends_with("_warehouse") => warehouses.push(warehouse.clone()),
+                    _ => collect_warehouses(field, warehouses),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_warehouses(item, warehouses)),
+        _ => {}
+    }
+}
+
+//handling units ("lpn", "parent_lpn") and return receipts ("receipt_id") a JSON body refers to
+#[derive(Debug, Default, PartialEq)]
+pub struct References{
+    pub lpns: Vec<String>,
+    pub receipt_ids: Vec<u64>,
+}
+
+impl References {
+    pub fn is_empty(&self) -> bool {
+        self.lpns.is_empty() && self.receipt_ids.is_empty()
+    }
+}
+
+pub fn named_references(body: &Value) -> References {
+    let mut references = References::default();
+    collect_references(body, &mut references);
+    references
+}
+
+fn collect_references(value: &Value, references: &mut References) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                match (name.as_str(), field) {
+                    ("lpn" | "parent_lpn", Value::String(lpn)) => references.lpns.push(lpn.clone()),
+                    ("receipt_id", Value::Number(id)) => references.receipt_ids.extend(id.as_u64()),
+                    _ => collect_references(field, references),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_references(item, references)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_need_the_listed_role() {
+        assert_eq!(rule_for(&Method::GET, "/unique_identifiers"), rule(Role::Viewer, false));
+        assert_eq!(rule_for(&Method::DELETE, "/remove_unique_identifiers"), rule(Role::Operator, true));
+        assert_eq!(rule_for(&Method::POST, "/products"), rule(Role::Admin, false));
+        assert_eq!(rule_for(&Method::GET, "/users").role, Role::Admin);
+    }
+
+    #[test]
+    fn warehouses_are_found_in_nested_bodies() {
+        let body = serde_json::json!({"warehouse": "HALA 5", "lines": [{"disposition_warehouse": "HALA 1"}], "location": "A-01"});
+
+        assert_eq!(named_warehouses(&body), vec!["HALA 1".to_string(), "HALA 5".to_string()]);
+        assert!(named_warehouses(&serde_json::json!({"lpn": "LPN-1"})).is_empty());
+    }
+
+    #[test]
+    fn handling_units_and_receipts_are_found_in_bodies() {
+        let nest = serde_json::json!({"lpn": "CAR-1", "parent_lpn": "PAL-1"});
+        assert_eq!(named_references(&nest).lpns, vec!["CAR-1".to_string(), "PAL-1".to_string()]);
+
+        let disposition = serde_json::json!({"receipt_id": 42, "disposition": "restock", "warehouse": "HALA 5"});
+        assert_eq!(named_references(&disposition), References { lpns: Vec::new(), receipt_ids: vec![42] });
+
+        assert!(named_references(&serde_json::json!({"lpn": null, "warehouse": "HALA 5"})).is_empty());
+        assert!(rule_for(&Method::POST, "/handling_units/pack").warehouse_scoped);
+        assert!(rule_for(&Method::POST, "/returns/receive").warehouse_scoped);
+    }
+}
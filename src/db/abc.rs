@@ -0,0 +1,56 @@
+use mysql_async::{prelude::Queryable, Error, params, TxOpts};
+
+use crate::db::database::Database;
+use crate::db::products::attributes_json;
+use crate::models::tenant::Tenant;
+use crate::models::abc::{classify, AbcAnalysisRequest, AbcClassification, ProductAbcClass, ProductOutbound};
+
+impl Database {
+    //RECOMPUTE ABC classes from outbound over the period and store them on products
+    pub async fn recompute_abc_classification(&self, tenant: &Tenant, request: &AbcAnalysisRequest) -> Result<Vec<AbcClassification>, Error> {
+        // Outbound value uses the average receipt cost of the product, kept as DECIMAL like the costs
+        let query = "SELECT p.product_code,
+                            CAST(COALESCE(o.pcs, 0) AS SIGNED) AS pcs,
+                            CAST(COALESCE(o.pcs, 0) * COALESCE(c.unit_cost, 0) AS DECIMAL(18, 4)) AS value
+                     FROM products p
+                     LEFT JOIN (SELECT product_code, SUM(-pcs) AS pcs FROM cost_movements
+                                WHERE tenant_id = :tenant_id AND movement_type = 'ISSUE' AND moved_at >= DATE_SUB(NOW(), INTERVAL :period_days DAY)
+                                GROUP BY product_code) o ON o.product_code = p.product_code
+                     LEFT JOIN (SELECT product_code, SUM(pcs * unit_cost) / SUM(pcs) AS unit_cost FROM cost_movements
+                                WHERE tenant_id = :tenant_id AND movement_type = 'RECEIPT' AND pcs > 0
+                                GROUP BY product_code) c ON c.product_code = p.product_code
+                     WHERE p.tenant_id = :tenant_id";
+
+        let mut conn = self.conn().await?;
+        let outbound: Vec<ProductOutbound> = conn.exec(query, params! { "tenant_id" => tenant.id(), "period_days" => request.period_days }).await?;
+
+        let classification = classify(&outbound, request);
+
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        tx.exec_batch(
+            "UPDATE products SET abc_class = :abc_class, abc_measure = :abc_measure, abc_computed_at = NOW() WHERE tenant_id = :tenant_id AND product_code = :product_code",
+            classification.iter().map(|item| params! {
+                "tenant_id" => tenant.id(),
+                "abc_class" => item.abc_class.as_db_str(),
+                "abc_measure" => item.measure,
+                "product_code" => &item.product_code,
+            }),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(classification)
+    }
+
+    //get stored ABC classes of all products, A first
+    pub async fn get_abc_classification(&self, tenant: &Tenant) -> Result<Vec<ProductAbcClass>, Error> {
+        let query = format!(
+            "SELECT p.product_code, p.product_name, {} AS attributes, p.abc_class, p.abc_measure, p.abc_computed_at
+             FROM products p WHERE p.tenant_id = :tenant_id ORDER BY p.abc_class IS NULL, p.abc_class, p.abc_measure DESC, p.product_code",
+            attributes_json("p"),
+        );
+
+        let mut conn = self.conn().await?;
+
+        conn.exec(query, params! { "tenant_id" => tenant.id() }).await
+    }
+}
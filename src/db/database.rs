@@ -1,33 +1,220 @@
-use std::env;
-use dotenv::dotenv;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::NaiveDate;
 
-use mysql_async::{prelude::Queryable, Error, Value, params};
+use mysql_async::{prelude::Queryable, Error, Value, params, TxOpts, Opts, OptsBuilder, PoolConstraints, PoolOpts};
 use crate::models::outgoing::RemoveUniqueIdentifierRequest;
+use crate::db::serials::{is_serial_tracked, check_serials, receive_serials, ship_serials};
+use crate::db::packaging::quantity_in_pcs;
+use crate::db::handling_units::packed_pcs;
+use crate::models::handling_units::loose_pcs;
+use crate::db::valuation::{record_issue, record_receipt};
+use crate::db::products::{attributes_json, resolve_product_code};
+use crate::config::DatabaseConfig;
+use crate::db::pool::{PoolUsage, PooledConn};
+use crate::metrics::Metrics;
 
 // ----------  TEST 1, TEST 2 -------- //
 use crate::models::incoming::{UniqueIdentifier,AddOrUpdateUniqueIdentifierRequest};
+use crate::models::stock_status::StockStatus;
+use crate::models::tenant::Tenant;
+use crate::models::empty_stock::EmptyRowMode;
+use crate::models::errors::ApiError;
 
 
+// Builds the primary key of a unique_identifiers row. Lot and expiry are only
+// appended when present and status only when it is not available, so keys of
+// plain available stock stay short.
+pub fn build_concatenated_string(product_code: &str, warehouse: &str, location: &str, lot_number: Option<&str>, expiry_date: Option<NaiveDate>, status: StockStatus) -> String {
+    let base = format!("{}^{}^{}", product_code, warehouse, location);
+
+    if lot_number.is_none() && expiry_date.is_none() && status == StockStatus::Available {
+        return base;
+    }
+
+    let expiry = expiry_date.map(|date| date.to_string()).unwrap_or_default();
+    let with_lot = format!("{}^{}^{}", base, lot_number.unwrap_or_default(), expiry);
+
+    if status == StockStatus::Available {
+        return with_lot;
+    }
+
+    format!("{}^{}", with_lot, status.as_db_str())
+}
+
+//stock rows of the tenant with product name and attributes of the variant, narrowed down by the given conditions
+pub(crate) fn select_stock(conditions: &str) -> String {
+    format!(
+        "SELECT u.*, p.product_name, {} AS attributes FROM unique_identifiers u JOIN products p ON p.tenant_id = u.tenant_id AND p.product_code = u.product_code WHERE u.tenant_id = :tenant_id {}",
+        attributes_json("p"), conditions,
+    )
+}
+
+// Pcs added to and taken from stock per warehouse within one transaction. They are counted in
+// the metrics by Database::record_movements once the transaction is committed, so changes that
+// are rolled back are not counted
+#[derive(Default, Debug)]
+pub(crate) struct StockMovements{
+    added: Vec<(String, i32)>,
+    removed: Vec<(String, i32)>,
+}
+
+impl StockMovements {
+    pub(crate) fn added(&mut self, warehouse: &str, pcs: i32) {
+        self.added.push((warehouse.to_string(), pcs));
+    }
+
+    pub(crate) fn removed(&mut self, warehouse: &str, pcs: i32) {
+        self.removed.push((warehouse.to_string(), pcs));
+    }
+}
+
+//add pcs to a stock row, creating the row from the given identifier when it does not exist
+pub(crate) async fn add_pcs<Q: Queryable>(conn: &mut Q, tenant: &Tenant, row: &UniqueIdentifier, pcs: i32, movements: &mut StockMovements) -> Result<(), Error> {
+    let query = "INSERT INTO unique_identifiers (tenant_id, concatenated_string, product_code, warehouse, location, pcs, lot_number, expiry_date, status)
+                 VALUES (:tenant_id, :concatenated_string, :product_code, :warehouse, :location, :pcs, :lot_number, :expiry_date, :status)
+                 ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs), emptied_at = NULL";
+
+    conn.exec_drop(query, params! {
+        "tenant_id" => tenant.id(),
+        "concatenated_string" => &row.concatenated_string,
+        "product_code" => &row.product_code,
+        "warehouse" => &row.warehouse,
+        "location" => &row.location,
+        "pcs" => pcs,
+        "lot_number" => &row.lot_number,
+        "expiry_date" => row.expiry_date,
+        "status" => row.status.as_db_str(),
+    }).await?;
+
+    movements.added(&row.warehouse, pcs);
+    Ok(())
+}
+
+//take pcs from a stock row, the row is deleted, kept or archived when nothing is left
+pub(crate) async fn deduct_pcs<Q: Queryable>(conn: &mut Q, tenant: &Tenant, row: &UniqueIdentifier, pcs: i32, empty_rows: EmptyRowMode, movements: &mut StockMovements) -> Result<(), Error> {
+    conn.exec_drop(
+        "UPDATE unique_identifiers SET pcs = pcs - :pcs WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string",
+        params! { "pcs" => pcs, "tenant_id" => tenant.id(), "concatenated_string" => &row.concatenated_string },
+    ).await?;
+    clear_if_empty(conn, tenant, &row.concatenated_string, empty_rows).await?;
+
+    movements.removed(&row.warehouse, pcs);
+    Ok(())
+}
+
+//deletes, keeps or archives a stock row without pcs, depending on empty_rows in the [stock] section
+pub(crate) async fn clear_if_empty<Q: Queryable>(conn: &mut Q, tenant: &Tenant, concatenated_string: &str, empty_rows: EmptyRowMode) -> Result<(), Error> {
+    let row_params = params! { "tenant_id" => tenant.id(), "concatenated_string" => concatenated_string };
+
+    match empty_rows {
+        EmptyRowMode::Delete => {}
+        EmptyRowMode::Keep => {
+            return conn.exec_drop(
+                "UPDATE unique_identifiers SET pcs = 0, emptied_at = COALESCE(emptied_at, NOW())
+                 WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string AND pcs <= 0",
+                row_params,
+            ).await;
+        }
+        EmptyRowMode::Archive => {
+            conn.exec_drop(
+                "INSERT INTO stock_location_history (tenant_id, concatenated_string, product_code, warehouse, location, lot_number, expiry_date, status, emptied_at)
+                 SELECT tenant_id, concatenated_string, product_code, warehouse, location, lot_number, expiry_date, status, NOW() FROM unique_identifiers
+                 WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string AND pcs <= 0",
+                row_params.clone(),
+            ).await?;
+        }
+    }
+
+    conn.exec_drop(
+        "DELETE FROM unique_identifiers WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string AND pcs <= 0",
+        row_params,
+    ).await
+}
+
+// Waiting time between connection attempts at startup doubles up to this
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
 #[derive(Clone)]
 pub struct Database {
     pub pool: mysql_async::Pool,
+    pub usage: Arc<PoolUsage>,
+    pub empty_rows: EmptyRowMode,
+    pub metrics: Arc<Metrics>,
 }
 
 impl Database {
-    pub async fn init() -> Result<Self, mysql_async::Error> {
-        dotenv().ok();
-        let db_url = env::var("MYSQL_DB_URL").expect("MYSQL_DB_URL not set in .env file");
-        let pool = mysql_async::Pool::new(db_url.as_str());
-        Ok(Database { pool })
+    // Pool size and timeouts come from the [database] section. Pool::new does not connect,
+    // so the first connection is opened here and retried with backoff before giving up
+    pub async fn init(config: &DatabaseConfig) -> Result<Self, mysql_async::Error> {
+        let constraints = PoolConstraints::new(config.pool_min, config.pool_max)
+            .ok_or_else(|| Error::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Pool min can not be greater than pool max")))?;
+        let pool_opts = PoolOpts::default()
+            .with_constraints(constraints)
+            .with_inactive_connection_ttl(Duration::from_secs(config.idle_timeout_secs));
+        let opts = OptsBuilder::from_opts(Opts::from_url(&config.url)?).pool_opts(pool_opts);
+        let db = Database::with_pool(mysql_async::Pool::new(opts), config);
+
+        let connect_timeout = Duration::from_secs(config.connect_timeout_secs);
+        let mut backoff = Duration::from_secs(1);
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            match db.ping(connect_timeout).await {
+                Ok(_) => return Ok(db),
+                Err(err) if attempt <= config.connect_retries => {
+                    tracing::warn!(attempt, attempts = config.connect_retries + 1, error = %err, retry_in_secs = backoff.as_secs(), "Database not reachable");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn with_pool(pool: mysql_async::Pool, config: &DatabaseConfig) -> Database {
+        Database {
+            pool,
+            usage: Arc::new(PoolUsage::new(config.pool_min, config.pool_max)),
+            empty_rows: EmptyRowMode::default(),
+            metrics: Arc::new(Metrics::new()),
+        }
+    }
+
+    //what happens with emptied stock rows, from the [stock] section
+    pub fn with_empty_rows(self, empty_rows: EmptyRowMode) -> Database {
+        Database { empty_rows, ..self }
+    }
+
+    //metrics served on /metrics, stock movements are counted there
+    pub fn with_metrics(self, metrics: Arc<Metrics>) -> Database {
+        Database { metrics, ..self }
+    }
+
+    //counts the movements of a committed transaction in wms_stock_pcs_added_total and wms_stock_pcs_removed_total
+    pub(crate) fn record_movements(&self, tenant: &Tenant, movements: StockMovements) {
+        for (warehouse, pcs) in movements.added {
+            self.metrics.record_added(tenant, &warehouse, pcs);
+        }
+        for (warehouse, pcs) in movements.removed {
+            self.metrics.record_removed(tenant, &warehouse, pcs);
+        }
     }
+
+    //connection from the pool, counted in the pool usage until dropped
+    pub async fn conn(&self) -> Result<PooledConn, Error> {
+        PooledConn::get(&self.pool, &self.usage).await
+    }
+
     // -------------- DATABASE FUNCTIONS ------------------ //
     //functions for unique_identifiers to get all locations
 
-    pub async fn get_all_locations(&self) ->  Result<Vec<UniqueIdentifier>, Error> {
-        let query = "SELECT * FROM unique_identifiers";
-        let mut conn = self.pool.get_conn().await.unwrap();
+    pub async fn get_all_locations(&self, tenant: &Tenant) ->  Result<Vec<UniqueIdentifier>, Error> {
+        let query = select_stock("AND u.pcs > 0");
+        let mut conn = self.conn().await?;
 
-        let locations: Vec<UniqueIdentifier> = conn.query(query).await.unwrap();
+        let locations: Vec<UniqueIdentifier> = conn.exec(query, params! { "tenant_id" => tenant.id() }).await?;
 
         Ok(locations)
     }
@@ -46,7 +233,7 @@ impl Database {
     //         "product_name" => product_name,
     //     };
       
-    //     let mut conn = self.pool.get_conn().await?;
+    //     let mut conn = self.conn().await?;
 
     //     let locations: Vec<UniqueIdentifier> = conn.exec(query, named_params.clone()).await?;
 
@@ -67,101 +254,138 @@ impl Database {
     //         "product_code" => product_code,
     //     };
       
-    //     let mut conn = self.pool.get_conn().await?;
+    //     let mut conn = self.conn().await?;
 
        
 
     //     Ok(locations)
     // }
 
-    //ADD or UPDATE existing row in the database
-    pub async fn add_or_update_unique_identifier(&self, update_data: &AddOrUpdateUniqueIdentifierRequest) -> Result<(), mysql_async::Error> {
-        // Extract information from update_data
-        let color = &update_data.color;
-        let product_name = &update_data.product_name;
-        
+    //ADD or UPDATE existing row in the database, returns the pcs added
+    pub async fn add_or_update_unique_identifier(&self, tenant: &Tenant, update_data: &AddOrUpdateUniqueIdentifierRequest) -> Result<i32, ApiError> {
+        let mut conn = self.conn().await?;
+
         // ------- TEST 4 ----------- //
-        // WRITE SQL QUERY FOR PRODUCTS TABLE TO GET product_code THAT WILL BE ADDED TO unique_identifier TABLE
-        let query_product = "";
-        let params_product = params! {
-            "color" => color,
-            "product_name" => product_name,
-        };
-    
-        let mut conn = self.pool.get_conn().await?;
-        let product_code: Option<String> = conn.exec_first(query_product, params_product).await?;
+        // Stock rows refer to the product variant, given by product_code or by name and attributes
+        let product_code = resolve_product_code(&mut *conn, tenant, &update_data.product).await?;
+
+        // Quantity can be given in boxes or pallets, stock is always kept in pcs
+        let pcs = quantity_in_pcs(&mut *conn, tenant, Some(&product_code), update_data.pcs, update_data.unit).await?;
+
+        // Serial tracked products must name one serial per received piece
+        let serial_tracked = is_serial_tracked(&mut *conn, tenant, &product_code).await?;
+        check_serials(serial_tracked, update_data.serial_numbers.as_ref(), pcs)?;
     
         // Build the concatenated string based on the update data
-        let update_concatenated_string = format!(
-            "{}^{}^{}^{}",
-            update_data.color, update_data.product_name, update_data.warehouse, update_data.location
+        let update_concatenated_string = build_concatenated_string(
+            &product_code, &update_data.warehouse, &update_data.location,
+            update_data.lot_number.as_deref(), update_data.expiry_date, update_data.status,
         );
     
         // Insert or update the row using the MySQL
-        let query_unique_identifier = "INSERT INTO unique_identifiers (concatenated_string, product_code, color, product_name, warehouse, location, pcs) VALUES (?, ?, ?, ?, ?, ?, ?)
-                                        ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs)";
+        let query_unique_identifier = "INSERT INTO unique_identifiers (tenant_id, concatenated_string, product_code, warehouse, location, pcs, lot_number, expiry_date, status) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                                        ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs), emptied_at = NULL";
         let params_unique_identifier: Vec<_> = vec![
+            Value::from(tenant.id()),
             Value::from(&update_concatenated_string),
             Value::from(&product_code),
-            Value::from(&update_data.color),
-            Value::from(&update_data.product_name),
             Value::from(&update_data.warehouse),
             Value::from(&update_data.location),
-            Value::from(update_data.pcs),
+            Value::from(pcs),
+            Value::from(&update_data.lot_number),
+            Value::from(update_data.expiry_date),
+            Value::from(update_data.status.as_db_str()),
         ];
+
+        // Stock row and serials are written together or not at all
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        tx.exec_drop(query_unique_identifier, params_unique_identifier).await?;
+
+        if let Some(serials) = &update_data.serial_numbers {
+            receive_serials(&mut tx, tenant, &product_code, &update_data.warehouse, &update_data.location, serials).await?;
+        }
+
+        // Every receipt opens a cost layer for the valuation
+        record_receipt(&mut tx, tenant, &product_code, &update_data.warehouse, pcs, update_data.unit_cost).await?;
+
+        tx.commit().await?;
+
+        let mut movements = StockMovements::default();
+        movements.added(&update_data.warehouse, pcs);
+        self.record_movements(tenant, movements);
     
-        conn.exec_drop(query_unique_identifier, params_unique_identifier).await?;
-    
-        Ok(())
+        Ok(pcs)
     }
 
 
-    //REMOVE values from pcs column or REMOVE row from database if pcs = 0
-    pub async fn remove_unique_identifier(&self, update_data: &RemoveUniqueIdentifierRequest) -> Result<(), mysql_async::Error> {
+    //REMOVE values from pcs column, a row without pcs is deleted, kept or archived. Returns the pcs removed
+    pub async fn remove_unique_identifier(&self, tenant: &Tenant, update_data: &RemoveUniqueIdentifierRequest) -> Result<i32, ApiError> {
+        let mut conn = self.conn().await?;
+        let product_code = resolve_product_code(&mut *conn, tenant, &update_data.product).await?;
+
         // Build concatenated string based on the update data
-        let update_concatenated_string = format!(
-            "{}^{}^{}^{}",
-            update_data.color, update_data.product_name, update_data.warehouse, update_data.location
+        let update_concatenated_string = build_concatenated_string(
+            &product_code, &update_data.warehouse, &update_data.location,
+            update_data.lot_number.as_deref(), update_data.expiry_date, update_data.status,
         );
-    
+
+        // Quantity can be given in boxes or pallets, stock is always kept in pcs
+        let pcs = quantity_in_pcs(&mut *conn, tenant, Some(&product_code), update_data.pcs, update_data.unit).await?;
+
+        // Serial tracked products must name every serial that is taken out
+        let serial_tracked = is_serial_tracked(&mut *conn, tenant, &product_code).await?;
+        check_serials(serial_tracked, update_data.serial_numbers.as_ref(), pcs)?;
+
+        // The row stays locked until the removal is committed, so concurrent removals and packs
+        // wait for it instead of both passing the checks below
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
         // Check if the user's requested pcs is greater than the current value in the database
-        let check_current_pcs_query = "SELECT pcs FROM unique_identifiers WHERE concatenated_string = ?";
-        let check_current_pcs_params: Vec<Value> = vec![Value::from(&update_concatenated_string)];
-        let current_pcs: Option<i32> = self.pool.get_conn().await?.exec_first(check_current_pcs_query, check_current_pcs_params).await?;
-    
-        if let Some(current_pcs_value) = current_pcs {
-            if update_data.pcs > current_pcs_value {
-                // Respond error if there's not enough pcs for deduction
-                return Err(mysql_async::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Not enough pcs for deduction")));
-            }
-        } else {
+        let check_current_pcs_query = "SELECT pcs FROM unique_identifiers WHERE tenant_id = ? AND concatenated_string = ? FOR UPDATE";
+        let check_current_pcs_params: Vec<Value> = vec![Value::from(tenant.id()), Value::from(&update_concatenated_string)];
+        let current_row: Option<i32> = tx.exec_first(check_current_pcs_query, check_current_pcs_params).await?;
+
+        let current_pcs_value = match current_row {
+            Some(row) => row,
             // Handle the case where the concatenated_string is not found in the database
-            return Err(mysql_async::Error::from(std::io::Error::new(std::io::ErrorKind::NotFound, "Concatenated string not found")));
+            None => return Err(ApiError::NotFound(format!("Unique identifier {} not found", update_concatenated_string))),
+        };
+
+        if pcs > current_pcs_value {
+            // Respond error if there's not enough pcs for deduction
+            return Err(ApiError::InsufficientStock(format!("Not enough pcs for deduction, {} pcs in stock", current_pcs_value)));
+        }
+
+        // Pcs packed in handling units have to be unpacked before they can be removed
+        if pcs > loose_pcs(current_pcs_value, packed_pcs(&mut tx, tenant, &update_concatenated_string).await?) {
+            return Err(ApiError::InsufficientStock("Not enough loose pcs for deduction, unpack the handling unit first".to_string()));
         }
+
+        if let Some(serials) = &update_data.serial_numbers {
+            ship_serials(&mut tx, tenant, &product_code, &update_data.warehouse, &update_data.location, serials, update_data.customer.as_deref()).await?;
+        }
+
+        record_issue(&mut tx, tenant, &product_code, &update_data.warehouse, pcs).await?;
     
-        // Update pcs field
-        let update_query = "UPDATE unique_identifiers SET pcs = pcs - ? WHERE concatenated_string = ?";
+        // Update pcs field, removal is the outbound movement of the row
+        let update_query = "UPDATE unique_identifiers SET pcs = pcs - ?, last_outbound_at = NOW() WHERE tenant_id = ? AND concatenated_string = ?";
         let update_params: Vec<_> = vec![
-            Value::from(update_data.pcs),
+            Value::from(pcs),
+            Value::from(tenant.id()),
             Value::from(&update_concatenated_string),
         ];
-        let mut conn = self.pool.get_conn().await?;
-        conn.exec_drop(update_query, update_params).await?;
+        tx.exec_drop(update_query, update_params).await?;
     
-        // Check if updated pcs is less than or equal to 0, and delete the row if necessary
-        let check_updated_pcs_query = "SELECT pcs FROM unique_identifiers WHERE concatenated_string = ?";
-        let check_updated_pcs_params: Vec<Value> = vec![Value::from(&update_concatenated_string)];
-        let updated_pcs: Option<i32> = conn.exec_first(check_updated_pcs_query, check_updated_pcs_params).await?;
-    
-        if let Some(pcs) = updated_pcs {
-            if pcs <= 0 {
-                let delete_query = "DELETE FROM unique_identifiers WHERE concatenated_string = ?";
-                let delete_params: Vec<Value> = vec![Value::from(&update_concatenated_string)];
-                conn.exec_drop(delete_query, delete_params).await?;
-            }
-        }
+        // Row without pcs is deleted, kept or archived with the date it was emptied
+        clear_if_empty(&mut tx, tenant, &update_concatenated_string, self.empty_rows).await?;
+
+        tx.commit().await?;
+
+        let mut movements = StockMovements::default();
+        movements.removed(&update_data.warehouse, pcs);
+        self.record_movements(tenant, movements);
     
-        Ok(())
+        Ok(pcs)
     }
 }
 
@@ -173,13 +397,37 @@ impl Database {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
+    use dotenv::dotenv;
+    use crate::db::pool::PoolStats;
 
         //database connection pool for testing
     async fn setup_test_database() -> Database {
         dotenv().ok();
         let db_url = env::var("MYSQL_DB_URL").expect("MYSQL_DB_URL not set in .env file");
         let pool = mysql_async::Pool::new(db_url.as_str());
-        Database { pool }
+        Database::with_pool(pool, &DatabaseConfig::default())
+    }
+
+    #[tokio::test]
+    async fn committed_movements_are_counted_per_warehouse() {
+        let metrics = Arc::new(Metrics::new());
+        // The pool connects lazily, recording movements does not reach the database
+        let db = Database::with_pool(mysql_async::Pool::new("mysql://localhost/wms"), &DatabaseConfig::default()).with_metrics(metrics.clone());
+        let tenant = Tenant::new("default").unwrap();
+
+        // A handling unit moved from HALA 5 to HALA 1 and a status change within HALA 5
+        let mut movements = StockMovements::default();
+        movements.removed("HALA 5", 4);
+        movements.added("HALA 1", 4);
+        movements.removed("HALA 5", 2);
+        movements.added("HALA 5", 2);
+        db.record_movements(&tenant, movements);
+
+        let text = metrics.render(&tenant, &PoolStats { min: 10, max: 100, in_use: 0, waiting: 0 }, &[]);
+        assert!(text.contains(r#"wms_stock_pcs_added_total{tenant="default",warehouse="HALA 1"} 4"#));
+        assert!(text.contains(r#"wms_stock_pcs_added_total{tenant="default",warehouse="HALA 5"} 2"#));
+        assert!(text.contains(r#"wms_stock_pcs_removed_total{tenant="default",warehouse="HALA 5"} 6"#));
     }
 
     #[tokio::test]
@@ -188,7 +436,8 @@ mod tests {
         let db = setup_test_database().await;
 
         // Act: Call the function you want to test
-        let result = db.get_all_locations().await;
+        let tenant = Tenant::new("default").unwrap();
+        let result = db.get_all_locations(&tenant).await;
         
        // Assert: Check if the result is as expected
         match result {
@@ -202,7 +451,6 @@ mod tests {
                     // Additional assertions for each location if needed
                     for location in locations {
                         assert!(!location.product_code.is_empty(), "Product code should not be empty");
-                        assert!(!location.color.is_empty(), "Product code is missing or empty");
                         assert!(!location.product_name.is_empty(), "Product code is missing or empty");
                         assert!(!location.warehouse.is_empty(), "Product code is missing or empty");
                         assert!(!location.location.is_empty(), "Product code is missing or empty");
@@ -275,6 +523,22 @@ mod tests {
     // }
 
 
+    #[test]
+    fn concatenated_string_includes_lot_expiry_and_status_only_when_present() {
+        let plain = build_concatenated_string("806807071423", "HALA 5", "M5-A-1", None, None, StockStatus::Available);
+        assert_eq!(plain, "806807071423^HALA 5^M5-A-1");
+
+        let expiry = NaiveDate::from_ymd_opt(2025, 3, 31);
+        let with_lot = build_concatenated_string("806807071423", "HALA 5", "M5-A-1", Some("L-42"), expiry, StockStatus::Available);
+        assert_eq!(with_lot, "806807071423^HALA 5^M5-A-1^L-42^2025-03-31");
+
+        let lot_only = build_concatenated_string("806807071423", "HALA 5", "M5-A-1", Some("L-42"), None, StockStatus::Available);
+        assert_eq!(lot_only, "806807071423^HALA 5^M5-A-1^L-42^");
+
+        let damaged = build_concatenated_string("806807071423", "HALA 5", "M5-A-1", None, None, StockStatus::Damaged);
+        assert_eq!(damaged, "806807071423^HALA 5^M5-A-1^^^DAMAGED");
+    }
+
     // ---------- TEST 3 --------------- //
     // WRITE TESTS FOR FUNCTIONS: add_or_update_unique_identifier(), remove_unique_identifier()
     
@@ -0,0 +1,50 @@
+use mysql_async::{prelude::Queryable, Error, params};
+
+use crate::db::database::{select_stock, Database};
+use crate::db::products::attributes_json;
+use crate::models::empty_stock::StockListQuery;
+use crate::models::incoming::UniqueIdentifier;
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //stock rows for the listing, empty rows only when asked for
+    pub async fn get_locations(&self, tenant: &Tenant, query: &StockListQuery) -> Result<Vec<UniqueIdentifier>, Error> {
+        if let Some(days) = query.emptied_within_days {
+            return self.get_recently_emptied(tenant, days).await;
+        }
+
+        if !query.include_empty {
+            return self.get_all_locations(tenant).await;
+        }
+
+        let mut conn = self.conn().await?;
+        conn.exec(select_stock(""), params! { "tenant_id" => tenant.id() }).await
+    }
+
+    //where products used to be: rows emptied within the last N days, kept at the location or archived
+    pub async fn get_recently_emptied(&self, tenant: &Tenant, days: i32) -> Result<Vec<UniqueIdentifier>, Error> {
+        let named_params = params! { "tenant_id" => tenant.id(), "days" => days };
+        let mut conn = self.conn().await?;
+
+        let mut emptied: Vec<UniqueIdentifier> = conn.exec(
+            select_stock("AND u.pcs = 0 AND u.emptied_at >= DATE_SUB(NOW(), INTERVAL :days DAY)"),
+            named_params.clone(),
+        ).await?;
+
+        let archived_query = format!(
+            "SELECT h.concatenated_string, h.product_code, p.product_name, {} AS attributes, h.warehouse, h.location, 0 AS pcs,
+                    h.lot_number, h.expiry_date, h.status, h.emptied_at
+             FROM stock_location_history h
+             JOIN products p ON p.tenant_id = h.tenant_id AND p.product_code = h.product_code
+             WHERE h.tenant_id = :tenant_id AND h.emptied_at >= DATE_SUB(NOW(), INTERVAL :days DAY)",
+            attributes_json("p"),
+        );
+        let archived: Vec<UniqueIdentifier> = conn.exec(archived_query, named_params).await?;
+
+        // Most recently emptied first, that is where the product is most likely to be found again
+        emptied.extend(archived);
+        emptied.sort_by_key(|row| std::cmp::Reverse(row.emptied_at));
+
+        Ok(emptied)
+    }
+}
@@ -0,0 +1,309 @@
+use mysql_async::{prelude::Queryable, Error, params, TxOpts};
+
+use crate::db::database::{add_pcs, build_concatenated_string, deduct_pcs, select_stock, Database, StockMovements};
+use crate::models::errors::ApiError;
+use crate::db::products::{attributes_json, resolve_product_code};
+use crate::db::serials::is_serial_tracked;
+use crate::db::valuation::record_transfer;
+use crate::models::handling_units::{
+    check_pack, check_parent, check_unpack, moved_row, CreateHandlingUnitRequest, GetHandlingUnit, HandlingUnit,
+    HandlingUnitContent, HandlingUnitDetails, HandlingUnitError, MoveHandlingUnitRequest, NestHandlingUnitRequest,
+    PackHandlingUnitRequest,
+};
+use crate::models::incoming::UniqueIdentifier;
+use crate::models::stock_status::StockStatus;
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //CREATE empty handling unit, optionally nested into a parent at the same location
+    pub async fn create_handling_unit(&self, tenant: &Tenant, request: &CreateHandlingUnitRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+
+        if let Some(parent_lpn) = &request.parent_lpn {
+            let parent = find_handling_unit(&mut *conn, tenant, parent_lpn).await?.ok_or_else(|| ApiError::NotFound("Parent LPN not found".to_string()))?;
+            check_parent(&request.warehouse, &request.location, &parent, &[]).map_err(rejected)?;
+        }
+
+        let query = "INSERT INTO handling_units (tenant_id, lpn, parent_lpn, unit_type, warehouse, location) VALUES (:tenant_id, :lpn, :parent_lpn, :unit_type, :warehouse, :location)";
+        conn.exec_drop(query, params! {
+            "tenant_id" => tenant.id(),
+            "lpn" => &request.lpn,
+            "parent_lpn" => &request.parent_lpn,
+            "unit_type" => request.unit_type.as_db_str(),
+            "warehouse" => &request.warehouse,
+            "location" => &request.location,
+        }).await?;
+
+        Ok(())
+    }
+
+    //warehouses the handling units are stored in now, unknown LPNs are left out
+    pub async fn handling_unit_warehouses(&self, tenant: &Tenant, lpns: &[String]) -> Result<Vec<String>, Error> {
+        let mut conn = self.conn().await?;
+        let mut warehouses = Vec::new();
+
+        for lpn in lpns {
+            let unit = find_handling_unit(&mut *conn, tenant, lpn).await?;
+            warehouses.extend(unit.map(|unit| unit.warehouse));
+        }
+
+        Ok(warehouses)
+    }
+
+    //get handling unit with its contents and all nested handling units
+    pub async fn get_handling_unit(&self, tenant: &Tenant, request: &GetHandlingUnit) -> Result<Option<HandlingUnitDetails>, Error> {
+        let mut conn = self.conn().await?;
+
+        let root = match find_handling_unit(&mut *conn, tenant, &request.lpn).await? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+
+        // Units are loaded breadth first and assembled bottom up
+        let mut levels: Vec<Vec<HandlingUnit>> = vec![vec![root]];
+        loop {
+            let mut next_level = Vec::new();
+            for unit in levels.last().unwrap() {
+                let children: Vec<HandlingUnit> = conn.exec(
+                    "SELECT lpn, parent_lpn, unit_type, warehouse, location FROM handling_units WHERE tenant_id = :tenant_id AND parent_lpn = :lpn ORDER BY lpn",
+                    params! { "tenant_id" => tenant.id(), "lpn" => &unit.lpn },
+                ).await?;
+                next_level.extend(children);
+            }
+            if next_level.is_empty() {
+                break;
+            }
+            levels.push(next_level);
+        }
+
+        let mut assembled: Vec<HandlingUnitDetails> = Vec::new();
+        while let Some(level) = levels.pop() {
+            let mut details_level = Vec::new();
+            for handling_unit in level {
+                let contents = load_contents(&mut *conn, tenant, &handling_unit.lpn).await?;
+                let (children, rest): (Vec<_>, Vec<_>) = assembled
+                    .into_iter()
+                    .partition(|child| child.handling_unit.parent_lpn.as_deref() == Some(handling_unit.lpn.as_str()));
+                assembled = rest;
+                details_level.push(HandlingUnitDetails { handling_unit, contents, children });
+            }
+            assembled = details_level;
+        }
+
+        Ok(assembled.pop())
+    }
+
+    //PACK loose stock at the handling unit location into the handling unit
+    pub async fn pack_handling_unit(&self, tenant: &Tenant, request: &PackHandlingUnitRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let handling_unit = find_handling_unit(&mut tx, tenant, &request.lpn).await?.ok_or_else(|| ApiError::NotFound("LPN not found".to_string()))?;
+        let product_code = resolve_product_code(&mut tx, tenant, &request.product).await?;
+        let concatenated_string = build_concatenated_string(
+            &product_code, &handling_unit.warehouse, &handling_unit.location,
+            request.lot_number.as_deref(), request.expiry_date, StockStatus::Available,
+        );
+
+        let stock_pcs: Option<i32> = tx.exec_first(
+            "SELECT pcs FROM unique_identifiers WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string FOR UPDATE",
+            params! { "tenant_id" => tenant.id(), "concatenated_string" => &concatenated_string },
+        ).await?;
+        let stock_pcs = stock_pcs.ok_or_else(|| ApiError::NotFound("Concatenated string not found".to_string()))?;
+
+        // Individual serials are not tracked inside handling units
+        if is_serial_tracked(&mut tx, tenant, &product_code).await? {
+            return Err(ApiError::invalid_input("Serial tracked products can not be packed into handling units"));
+        }
+
+        let packed = packed_pcs(&mut tx, tenant, &concatenated_string).await?;
+        check_pack(stock_pcs, packed, request.pcs).map_err(rejected)?;
+
+        tx.exec_drop(
+            "INSERT INTO handling_unit_contents (tenant_id, lpn, concatenated_string, pcs) VALUES (:tenant_id, :lpn, :concatenated_string, :pcs)
+             ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs)",
+            params! { "tenant_id" => tenant.id(), "lpn" => &request.lpn, "concatenated_string" => &concatenated_string, "pcs" => request.pcs },
+        ).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    //UNPACK stock from the handling unit, the pcs stay at the location as loose stock
+    pub async fn unpack_handling_unit(&self, tenant: &Tenant, request: &PackHandlingUnitRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let handling_unit = find_handling_unit(&mut tx, tenant, &request.lpn).await?.ok_or_else(|| ApiError::NotFound("LPN not found".to_string()))?;
+        let product_code = resolve_product_code(&mut tx, tenant, &request.product).await?;
+        let concatenated_string = build_concatenated_string(
+            &product_code, &handling_unit.warehouse, &handling_unit.location,
+            request.lot_number.as_deref(), request.expiry_date, StockStatus::Available,
+        );
+
+        let content_params = params! { "tenant_id" => tenant.id(), "lpn" => &request.lpn, "concatenated_string" => &concatenated_string };
+        let content_pcs: Option<i32> = tx.exec_first(
+            "SELECT pcs FROM handling_unit_contents WHERE tenant_id = :tenant_id AND lpn = :lpn AND concatenated_string = :concatenated_string FOR UPDATE",
+            content_params.clone(),
+        ).await?;
+
+        check_unpack(content_pcs, request.pcs).map_err(rejected)?;
+
+        tx.exec_drop(
+            "UPDATE handling_unit_contents SET pcs = pcs - :pcs WHERE tenant_id = :tenant_id AND lpn = :lpn AND concatenated_string = :concatenated_string",
+            params! { "tenant_id" => tenant.id(), "pcs" => request.pcs, "lpn" => &request.lpn, "concatenated_string" => &concatenated_string },
+        ).await?;
+        tx.exec_drop(
+            "DELETE FROM handling_unit_contents WHERE tenant_id = :tenant_id AND lpn = :lpn AND concatenated_string = :concatenated_string AND pcs <= 0",
+            content_params,
+        ).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    //NEST handling unit into a parent at the same location, or take it out of its parent
+    pub async fn nest_handling_unit(&self, tenant: &Tenant, request: &NestHandlingUnitRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let handling_unit = find_handling_unit(&mut tx, tenant, &request.lpn).await?.ok_or_else(|| ApiError::NotFound("LPN not found".to_string()))?;
+
+        if let Some(parent_lpn) = &request.parent_lpn {
+            let parent = find_handling_unit(&mut tx, tenant, parent_lpn).await?.ok_or_else(|| ApiError::NotFound("Parent LPN not found".to_string()))?;
+            let nested_lpns = collect_tree(&mut tx, tenant, &request.lpn).await?;
+            check_parent(&handling_unit.warehouse, &handling_unit.location, &parent, &nested_lpns).map_err(rejected)?;
+        }
+
+        tx.exec_drop(
+            "UPDATE handling_units SET parent_lpn = :parent_lpn WHERE tenant_id = :tenant_id AND lpn = :lpn",
+            params! { "tenant_id" => tenant.id(), "parent_lpn" => &request.parent_lpn, "lpn" => &request.lpn },
+        ).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    //MOVE handling unit with all nested units and contents, stock rows follow the contents
+    pub async fn move_handling_unit(&self, tenant: &Tenant, request: &MoveHandlingUnitRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        find_handling_unit(&mut tx, tenant, &request.lpn).await?.ok_or_else(|| ApiError::NotFound("LPN not found".to_string()))?;
+
+        let mut movements = StockMovements::default();
+        for lpn in collect_tree(&mut tx, tenant, &request.lpn).await? {
+            let contents: Vec<(String, i32)> = tx.exec(
+                "SELECT concatenated_string, pcs FROM handling_unit_contents WHERE tenant_id = :tenant_id AND lpn = :lpn FOR UPDATE",
+                params! { "tenant_id" => tenant.id(), "lpn" => &lpn },
+            ).await?;
+
+            for (source_string, pcs) in contents {
+                let source: UniqueIdentifier = tx.exec_first(
+                    select_stock("AND u.concatenated_string = :concatenated_string FOR UPDATE"),
+                    params! { "tenant_id" => tenant.id(), "concatenated_string" => &source_string },
+                ).await?.ok_or_else(|| ApiError::NotFound("Stock row of the handling unit not found".to_string()))?;
+
+                let Some(target_string) = moved_row(&source, &request.warehouse, &request.location) else {
+                    continue;
+                };
+
+                // Packed pcs leave the source row and are added to the row at the destination
+                let source_warehouse = source.warehouse.clone();
+                deduct_pcs(&mut tx, tenant, &source, pcs, self.empty_rows, &mut movements).await?;
+                let target = UniqueIdentifier {
+                    concatenated_string: target_string.clone(),
+                    warehouse: request.warehouse.clone(),
+                    location: request.location.clone(),
+                    ..source
+                };
+                add_pcs(&mut tx, tenant, &target, pcs, &mut movements).await?;
+
+                // Leaving the warehouse takes the cost along to the destination warehouse
+                if target.warehouse != source_warehouse {
+                    record_transfer(&mut tx, tenant, &target.product_code, &source_warehouse, &target.warehouse, pcs).await?;
+                }
+
+                // Contents now point to the stock row at the new location
+                tx.exec_drop(
+                    "DELETE FROM handling_unit_contents WHERE tenant_id = :tenant_id AND lpn = :lpn AND concatenated_string = :concatenated_string",
+                    params! { "tenant_id" => tenant.id(), "lpn" => &lpn, "concatenated_string" => &source_string },
+                ).await?;
+                tx.exec_drop(
+                    "INSERT INTO handling_unit_contents (tenant_id, lpn, concatenated_string, pcs) VALUES (:tenant_id, :lpn, :concatenated_string, :pcs)
+                     ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs)",
+                    params! { "tenant_id" => tenant.id(), "lpn" => &lpn, "concatenated_string" => &target_string, "pcs" => pcs },
+                ).await?;
+            }
+
+            tx.exec_drop(
+                "UPDATE handling_units SET warehouse = :warehouse, location = :location WHERE tenant_id = :tenant_id AND lpn = :lpn",
+                params! { "tenant_id" => tenant.id(), "warehouse" => &request.warehouse, "location" => &request.location, "lpn" => &lpn },
+            ).await?;
+        }
+
+        // Moved unit leaves its parent, the parent stays where it was
+        tx.exec_drop(
+            "UPDATE handling_units SET parent_lpn = NULL WHERE tenant_id = :tenant_id AND lpn = :lpn",
+            params! { "tenant_id" => tenant.id(), "lpn" => &request.lpn },
+        ).await?;
+
+        tx.commit().await?;
+        self.record_movements(tenant, movements);
+
+        Ok(())
+    }
+}
+
+//missing pcs answer like a removal without stock, a product that is not in the unit is not found
+fn rejected(error: HandlingUnitError) -> ApiError {
+    match error {
+        HandlingUnitError::NotEnoughLoosePcs(_) | HandlingUnitError::NotEnoughPackedPcs(_) => ApiError::InsufficientStock(error.to_string()),
+        HandlingUnitError::NotPacked => ApiError::NotFound(error.to_string()),
+        _ => ApiError::invalid_input(&error.to_string()),
+    }
+}
+
+async fn find_handling_unit<Q: Queryable>(conn: &mut Q, tenant: &Tenant, lpn: &str) -> Result<Option<HandlingUnit>, Error> {
+    let query = "SELECT lpn, parent_lpn, unit_type, warehouse, location FROM handling_units WHERE tenant_id = :tenant_id AND lpn = :lpn";
+    conn.exec_first(query, params! { "tenant_id" => tenant.id(), "lpn" => lpn }).await
+}
+
+async fn load_contents<Q: Queryable>(conn: &mut Q, tenant: &Tenant, lpn: &str) -> Result<Vec<HandlingUnitContent>, Error> {
+    let query = format!(
+        "SELECT c.concatenated_string, u.product_code, p.product_name, {} AS attributes, u.lot_number, u.expiry_date, c.pcs
+         FROM handling_unit_contents c JOIN unique_identifiers u ON u.tenant_id = c.tenant_id AND u.concatenated_string = c.concatenated_string
+         JOIN products p ON p.tenant_id = u.tenant_id AND p.product_code = u.product_code
+         WHERE c.tenant_id = :tenant_id AND c.lpn = :lpn ORDER BY c.concatenated_string",
+        attributes_json("p"),
+    );
+    conn.exec(query, params! { "tenant_id" => tenant.id(), "lpn" => lpn }).await
+}
+
+//LPN together with every handling unit nested in it, at any depth
+async fn collect_tree<Q: Queryable>(conn: &mut Q, tenant: &Tenant, lpn: &str) -> Result<Vec<String>, Error> {
+    let mut tree = vec![lpn.to_string()];
+    let mut index = 0;
+
+    while index < tree.len() {
+        let children: Vec<String> = conn.exec(
+            "SELECT lpn FROM handling_units WHERE tenant_id = :tenant_id AND parent_lpn = :lpn",
+            params! { "tenant_id" => tenant.id(), "lpn" => &tree[index] },
+        ).await?;
+        tree.extend(children);
+        index += 1;
+    }
+
+    Ok(tree)
+}
+
+//pcs of a stock row that are packed in handling units
+pub(crate) async fn packed_pcs<Q: Queryable>(conn: &mut Q, tenant: &Tenant, concatenated_string: &str) -> Result<i32, Error> {
+    let query = "SELECT CAST(COALESCE(SUM(pcs), 0) AS SIGNED) FROM handling_unit_contents WHERE tenant_id = :tenant_id AND concatenated_string = :concatenated_string";
+    let packed: Option<i64> = conn.exec_first(query, params! { "tenant_id" => tenant.id(), "concatenated_string" => concatenated_string }).await?;
+
+    Ok(packed.unwrap_or(0) as i32)
+}
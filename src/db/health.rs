@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use mysql_async::{prelude::Queryable, Error};
+
+use crate::db::database::Database;
+use crate::models::health::{DatabaseCheck, Readiness, SchemaCheck};
+
+// Raised together with the INSERT INTO schema_version line of every new migration in "SQL code.txt"
+pub const SCHEMA_VERSION: u32 = 7;
+
+// Error reported by /readyz whatever the reason, connection details are not shown
+const DATABASE_UNAVAILABLE: &str = "database unavailable";
+
+impl Database {
+    //round trip to MySQL, a new connection is opened when the pool has none
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, Error> {
+        let started = Instant::now();
+
+        let ping = async {
+            let mut conn = self.conn().await?;
+            conn.ping().await
+        };
+
+        match tokio::time::timeout(timeout, ping).await {
+            Ok(result) => result.map(|_| started.elapsed()),
+            Err(_) => Err(Error::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "Database did not answer in time"))),
+        }
+    }
+
+    //version of the last migration applied, schema_version is shared by all tenants
+    pub async fn get_schema_version(&self) -> Result<Option<u32>, Error> {
+        let mut conn = self.conn().await?;
+        let version: Option<Option<u32>> = conn.query_first("SELECT MAX(version) FROM schema_version").await?;
+
+        Ok(version.flatten())
+    }
+
+    pub async fn readiness(&self, timeout: Duration) -> Readiness {
+        let database = match self.ping(timeout).await {
+            Ok(latency) => DatabaseCheck { reachable: true, latency_ms: Some(latency.as_millis()), error: None },
+            // /readyz is public, the cause only goes to the log
+            Err(err) => {
+                tracing::error!(error = %err, "readiness check could not reach the database");
+                DatabaseCheck { reachable: false, latency_ms: None, error: Some(DATABASE_UNAVAILABLE.to_string()) }
+            }
+        };
+
+        let found = match database.reachable {
+            true => self.get_schema_version().await.ok().flatten(),
+            false => None,
+        };
+
+        Readiness::new(database, SchemaCheck { expected: SCHEMA_VERSION, found }, self.usage.stats())
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{Local, NaiveDate};
+use mysql_async::{prelude::Queryable, Error, params};
+
+use crate::db::database::{select_stock, Database};
+use crate::db::handling_units::packed_pcs;
+use crate::models::handling_units::loose_pcs;
+use crate::models::errors::ApiError;
+use crate::models::incoming::{UniqueIdentifier, GetCodeAndPcsForSalesOrder};
+use crate::models::lots::{GetExpiringStock, StockAllocation};
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //get stock rows that expire within the requested number of days (already expired rows included)
+    pub async fn get_expiring_stock(&self, tenant: &Tenant, request: &GetExpiringStock) -> Result<Vec<UniqueIdentifier>, Error> {
+        let query = select_stock(
+            "AND u.pcs > 0 AND u.expiry_date IS NOT NULL AND u.expiry_date <= DATE_ADD(CURDATE(), INTERVAL :days DAY)
+             ORDER BY u.expiry_date ASC, u.warehouse ASC, u.location ASC",
+        );
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "days" => request.days,
+        };
+
+        let mut conn = self.conn().await?;
+        let locations: Vec<UniqueIdentifier> = conn.exec(query, named_params).await?;
+
+        Ok(locations)
+    }
+
+    //FEFO allocation: pick the rows that expire first, rows without expiry date are picked last.
+    //Only available stock is allocated, quarantined, blocked, damaged and expired rows are skipped
+    pub async fn allocate_fefo(&self, tenant: &Tenant, request: &GetCodeAndPcsForSalesOrder) -> Result<Vec<StockAllocation>, ApiError> {
+        let query = select_stock(
+            "AND u.product_code = :product_code AND u.pcs > 0 AND u.status = 'AVAILABLE'
+             AND (u.expiry_date IS NULL OR u.expiry_date >= CURDATE())
+             ORDER BY u.expiry_date IS NULL, u.expiry_date ASC, u.warehouse ASC, u.location ASC",
+        );
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => &request.product_code,
+        };
+
+        let mut conn = self.conn().await?;
+        let mut candidates: Vec<UniqueIdentifier> = conn.exec(query, named_params).await?;
+
+        // Pcs packed in handling units can not be removed until they are unpacked, so they are not promised
+        for row in &mut candidates {
+            row.pcs = loose_pcs(row.pcs, packed_pcs(&mut *conn, tenant, &row.concatenated_string).await?);
+        }
+
+        let allocations = pick_fefo(candidates, request.pcs, Local::now().date_naive());
+        let allocated: i32 = allocations.iter().map(|allocation| allocation.pcs).sum();
+
+        if allocated < request.pcs {
+            // Respond error if there's not enough pcs in stock for the whole order
+            return Err(ApiError::InsufficientStock("Not enough pcs for allocation".to_string()));
+        }
+
+        Ok(allocations)
+    }
+}
+
+// Takes pcs from already sorted rows until the requested quantity is covered. Rows without
+// loose pcs and rows that expired before today are never picked
+fn pick_fefo(candidates: Vec<UniqueIdentifier>, requested_pcs: i32, today: NaiveDate) -> Vec<StockAllocation> {
+    let mut remaining = requested_pcs;
+    let mut allocations = Vec::new();
+
+    for row in candidates {
+        if remaining <= 0 {
+            break;
+        }
+        if row.pcs <= 0 || row.expiry_date.is_some_and(|expiry_date| expiry_date < today) {
+            continue;
+        }
+
+        let pcs = row.pcs.min(remaining);
+        remaining -= pcs;
+
+        allocations.push(StockAllocation {
+            concatenated_string: row.concatenated_string,
+            warehouse: row.warehouse,
+            location: row.location,
+            lot_number: row.lot_number,
+            expiry_date: row.expiry_date,
+            pcs,
+        });
+    }
+
+    allocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::models::stock_status::StockStatus;
+
+    fn lot(location: &str, expiry_date: Option<NaiveDate>, pcs: i32) -> UniqueIdentifier {
+        UniqueIdentifier {
+            concatenated_string: format!("806807071423^HALA 5^{}", location),
+            product_code: "806807071423".to_string(),
+            product_name: "Smart thermostat".to_string(),
+            attributes: BTreeMap::new(),
+            warehouse: "HALA 5".to_string(),
+            location: location.to_string(),
+            pcs,
+            lot_number: None,
+            expiry_date,
+            status: StockStatus::Available,
+            emptied_at: None,
+            breakdown: None,
+        }
+    }
+
+    fn date(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2025, 3, day)
+    }
+
+    #[test]
+    fn lots_are_taken_in_order_and_the_last_one_partially() {
+        let allocations = pick_fefo(vec![lot("A-1", date(10), 4), lot("A-2", date(20), 10), lot("A-3", None, 5)], 7, date(1).unwrap());
+
+        let picked: Vec<(&str, i32)> = allocations.iter().map(|allocation| (allocation.location.as_str(), allocation.pcs)).collect();
+        assert_eq!(picked, vec![("A-1", 4), ("A-2", 3)]);
+    }
+
+    #[test]
+    fn lots_that_run_short_allocate_what_there_is() {
+        let allocations = pick_fefo(vec![lot("A-1", date(10), 4), lot("A-2", None, 0)], 9, date(1).unwrap());
+
+        assert_eq!(allocations.iter().map(|allocation| allocation.pcs).sum::<i32>(), 4);
+        assert_eq!(allocations.len(), 1);
+    }
+
+    #[test]
+    fn expired_lots_are_not_allocated() {
+        let allocations = pick_fefo(vec![lot("A-1", date(10), 4), lot("A-2", date(15), 4), lot("A-3", None, 4)], 6, date(15).unwrap());
+
+        let picked: Vec<(&str, i32)> = allocations.iter().map(|allocation| (allocation.location.as_str(), allocation.pcs)).collect();
+        assert_eq!(picked, vec![("A-2", 4), ("A-3", 2)]);
+    }
+}
@@ -1 +1,17 @@
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod lots;
+pub mod serials;
+pub mod products;
+pub mod packaging;
+pub mod handling_units;
+pub mod stock_status;
+pub mod returns;
+pub mod valuation;
+pub mod reports;
+pub mod abc;
+pub mod empty_stock;
+pub mod pool;
+pub mod health;
+pub mod users;
+#[cfg(test)]
+mod tenant_isolation;
\ No newline at end of file
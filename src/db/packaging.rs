@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use mysql_async::{prelude::Queryable, Error, params};
+
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::incoming::UniqueIdentifier;
+use crate::models::packaging::{GetPackaging, Packaging, PackagingDefinition, SetPackagingRequest, Unit};
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //ADD or UPDATE packaging definition of a product
+    pub async fn set_packaging(&self, tenant: &Tenant, request: &SetPackagingRequest) -> Result<(), ApiError> {
+        let valid_hierarchy = matches!(
+            (request.unit, request.contains_unit),
+            (Unit::Box, Unit::Pcs) | (Unit::Pallet, Unit::Pcs) | (Unit::Pallet, Unit::Box)
+        );
+
+        if !valid_hierarchy {
+            return Err(ApiError::invalid_input("Boxes must contain pcs and pallets must contain boxes or pcs"));
+        }
+
+        let query = "INSERT INTO product_packaging (tenant_id, product_code, unit, quantity, contains_unit) VALUES (:tenant_id, :product_code, :unit, :quantity, :contains_unit)
+                     ON DUPLICATE KEY UPDATE quantity = VALUES(quantity), contains_unit = VALUES(contains_unit)";
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => &request.product_code,
+            "unit" => request.unit.as_db_str(),
+            "quantity" => request.quantity,
+            "contains_unit" => request.contains_unit.as_db_str(),
+        };
+
+        let mut conn = self.conn().await?;
+        conn.exec_drop(query, named_params).await?;
+
+        Ok(())
+    }
+
+    //get packaging definitions of a product
+    pub async fn get_packaging(&self, tenant: &Tenant, request: &GetPackaging) -> Result<Vec<PackagingDefinition>, Error> {
+        let mut conn = self.conn().await?;
+        load_definitions(&mut *conn, tenant, &request.product_code).await
+    }
+
+    //fill in pallets/boxes/pieces breakdown for every stock row
+    pub async fn attach_breakdown(&self, tenant: &Tenant, locations: &mut [UniqueIdentifier]) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let mut packaging_by_code: HashMap<String, Packaging> = HashMap::new();
+
+        for location in locations.iter_mut() {
+            if !packaging_by_code.contains_key(&location.product_code) {
+                let definitions = load_definitions(&mut *conn, tenant, &location.product_code).await?;
+                packaging_by_code.insert(location.product_code.clone(), Packaging::from_definitions(&definitions));
+            }
+
+            location.breakdown = Some(packaging_by_code[&location.product_code].breakdown(location.pcs));
+        }
+
+        Ok(())
+    }
+}
+
+async fn load_definitions<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str) -> Result<Vec<PackagingDefinition>, Error> {
+    let query = "SELECT product_code, unit, quantity, contains_unit FROM product_packaging WHERE tenant_id = :tenant_id AND product_code = :product_code";
+    conn.exec(query, params! { "tenant_id" => tenant.id(), "product_code" => product_code }).await
+}
+
+//convert a requested quantity to pieces using the packaging of the product
+pub(crate) async fn quantity_in_pcs<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: Option<&str>, quantity: i32, unit: Unit) -> Result<i32, ApiError> {
+    if unit == Unit::Pcs {
+        return Ok(quantity);
+    }
+
+    let packaging = match product_code {
+        Some(product_code) => Packaging::from_definitions(&load_definitions(conn, tenant, product_code).await?),
+        None => Packaging::default(),
+    };
+
+    packaging.pcs_in(quantity, unit).ok_or_else(|| {
+        ApiError::invalid_input("Unit is not defined in the packaging of the product")
+    })
+}
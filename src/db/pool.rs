@@ -0,0 +1,94 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use mysql_async::{Conn, Error, Pool};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// mysql_async does not report how many connections are in use, so connections
+// taken through Database::conn are counted here for /readyz
+#[derive(Debug, Default)]
+pub struct PoolUsage{
+    min: usize,
+    max: usize,
+    in_use: AtomicUsize,
+    waiting: AtomicUsize,
+}
+
+//snapshot of the pool usage
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct PoolStats{
+    pub min: usize,
+    pub max: usize,
+    pub in_use: usize,
+    pub waiting: usize,
+}
+
+impl PoolUsage {
+    pub fn new(min: usize, max: usize) -> PoolUsage {
+        PoolUsage { min, max, ..PoolUsage::default() }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            min: self.min,
+            max: self.max,
+            in_use: self.in_use.load(Ordering::Relaxed),
+            waiting: self.waiting.load(Ordering::Relaxed),
+        }
+    }
+}
+
+//connection taken from the pool, counted as in use until it is dropped
+pub struct PooledConn{
+    conn: Conn,
+    usage: Arc<PoolUsage>,
+}
+
+impl PooledConn {
+    pub(crate) async fn get(pool: &Pool, usage: &Arc<PoolUsage>) -> Result<PooledConn, Error> {
+        let waiting = Counted::new(&usage.waiting);
+        let conn = pool.get_conn().await?;
+        drop(waiting);
+
+        usage.in_use.fetch_add(1, Ordering::Relaxed);
+        Ok(PooledConn { conn, usage: usage.clone() })
+    }
+}
+
+// Decrements on drop, so a request given up on while waiting (e.g. a timeout) is not counted forever
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(counter: &'a AtomicUsize) -> Counted<'a> {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Counted(counter)
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Deref for PooledConn {
+    type Target = Conn;
+
+    fn deref(&self) -> &Conn {
+        &self.conn
+    }
+}
+
+impl DerefMut for PooledConn {
+    fn deref_mut(&mut self) -> &mut Conn {
+        &mut self.conn
+    }
+}
+
+impl Drop for PooledConn {
+    fn drop(&mut self) {
+        self.usage.in_use.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mysql_async::{prelude::Queryable, Error, Params, TxOpts, Value, params};
+
+use crate::config::ProductsConfig;
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::product_code::{is_valid_company_prefix, next_product_code};
+use crate::models::gs1::gtin_candidates;
+use crate::models::products::{
+    matches_attributes, AddProductRequest, CreateProductFamilyRequest, ProductFamily, ProductFamilyDetails,
+    ProductRef, Products, SetProductAttributesRequest,
+};
+use crate::models::tenant::Tenant;
+
+// MySQL error code for a duplicate primary key
+const ER_DUP_ENTRY: u16 = 1062;
+// How many times a generated code is retried when another request took it first
+const GENERATE_ATTEMPTS: usize = 5;
+
+//JSON object with the attributes of the product under the given table alias, NULL without attributes
+pub(crate) fn attributes_json(alias: &str) -> String {
+    format!("(SELECT JSON_OBJECTAGG(a.attribute_name, a.attribute_value) FROM product_attributes a WHERE a.tenant_id = {alias}.tenant_id AND a.product_code = {alias}.product_code)")
+}
+
+//products of the tenant with their attributes, narrowed down by the given conditions
+fn product_query(conditions: &str) -> String {
+    format!(
+        "SELECT p.product_code, p.product_name, p.family_code, {} AS attributes FROM products p WHERE p.tenant_id = :tenant_id {}",
+        attributes_json("p"), conditions,
+    )
+}
+
+//product_code of the variant a stock request refers to, by code or by name and attributes
+pub(crate) async fn resolve_product_code<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product: &ProductRef) -> Result<String, ApiError> {
+    if let Some(product_code) = &product.product_code {
+        let found: Option<String> = conn.exec_first(
+            "SELECT product_code FROM products WHERE tenant_id = :tenant_id AND product_code = :product_code",
+            params! { "tenant_id" => tenant.id(), "product_code" => product_code },
+        ).await?;
+
+        return found.ok_or_else(|| ApiError::NotFound("Product not found".to_string()));
+    }
+
+    let candidates: Vec<Products> = conn.exec(
+        product_query("AND p.product_name = :product_name"),
+        params! { "tenant_id" => tenant.id(), "product_name" => product.product_name.as_deref().unwrap_or_default() },
+    ).await?;
+
+    let requested = product.variant_attributes();
+    let mut matching = candidates.into_iter().filter(|candidate| matches_attributes(&candidate.attributes, &requested));
+
+    match (matching.next(), matching.next()) {
+        (Some(variant), None) => Ok(variant.product_code),
+        (None, _) => Err(ApiError::NotFound("Product not found".to_string())),
+        (Some(_), Some(_)) => Err(ApiError::invalid_input("Product name and attributes match several variants, product_code is required")),
+    }
+}
+
+//a family can not hold two variants with the same attributes
+async fn check_variant_is_new<Q: Queryable>(conn: &mut Q, tenant: &Tenant, family_code: &str, attributes: &BTreeMap<String, String>, product_code: Option<&str>) -> Result<(), ApiError> {
+    let variants: Vec<Products> = conn.exec(
+        product_query("AND p.family_code = :family_code"),
+        params! { "tenant_id" => tenant.id(), "family_code" => family_code },
+    ).await?;
+
+    let duplicate = variants.iter().any(|variant| {
+        Some(variant.product_code.as_str()) != product_code && &variant.attributes == attributes
+    });
+
+    if duplicate {
+        return Err(ApiError::Conflict("A variant with the same attributes already exists in the family".to_string()));
+    }
+
+    Ok(())
+}
+
+//add or overwrite attributes of a product, attributes that are not given are kept
+async fn upsert_attributes<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, attributes: &BTreeMap<String, String>) -> Result<(), Error> {
+    let query = "INSERT INTO product_attributes (tenant_id, product_code, attribute_name, attribute_value) VALUES (:tenant_id, :product_code, :attribute_name, :attribute_value)
+                 ON DUPLICATE KEY UPDATE attribute_value = VALUES(attribute_value)";
+
+    conn.exec_batch(query, attributes.iter().map(|(name, value)| params! {
+        "tenant_id" => tenant.id(),
+        "product_code" => product_code,
+        "attribute_name" => name,
+        "attribute_value" => value,
+    })).await
+}
+
+async fn insert_product<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, request: &AddProductRequest) -> Result<(), Error> {
+    conn.exec_drop(
+        "INSERT INTO products (tenant_id, product_code, product_name, family_code) VALUES (:tenant_id, :product_code, :product_name, :family_code)",
+        params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => product_code,
+            "product_name" => &request.product_name,
+            "family_code" => &request.family_code,
+        },
+    ).await
+}
+
+//insert the product under the next free product_code in the company prefix
+async fn insert_generated_product<Q: Queryable>(conn: &mut Q, tenant: &Tenant, request: &AddProductRequest, config: &ProductsConfig) -> Result<String, ApiError> {
+    // Every company has its own GS1 prefix, the one in the [products] section is used for tenants without one.
+    // Locking the tenant row makes concurrent requests of the tenant generate one after the other
+    let tenant_prefix: Option<Option<String>> = conn.exec_first(
+        "SELECT gs1_company_prefix FROM tenants WHERE tenant_id = :tenant_id FOR UPDATE",
+        params! { "tenant_id" => tenant.id() },
+    ).await?;
+    let company_prefix = match tenant_prefix.flatten() {
+        Some(prefix) => prefix,
+        None if !config.gs1_company_prefix.is_empty() => config.gs1_company_prefix.clone(),
+        None => return Err(ApiError::invalid_input("No GS1 company prefix is set for the tenant, product_code is required")),
+    };
+    let code_length = config.gs1_code_length;
+
+    if !is_valid_company_prefix(&company_prefix, code_length) {
+        return Err(ApiError::invalid_input("The GS1 company prefix of the tenant must be digits and shorter than the code length minus the check digit"));
+    }
+
+    // A locking read sees the latest committed codes, a plain SELECT would re-read the snapshot of
+    // the transaction and generate the same code on every attempt
+    let existing_query = "SELECT product_code FROM products WHERE tenant_id = :tenant_id AND product_code LIKE CONCAT(:company_prefix, '%') FOR UPDATE";
+
+    for _ in 0..GENERATE_ATTEMPTS {
+        let existing_codes: Vec<String> = conn.exec(existing_query, params! { "tenant_id" => tenant.id(), "company_prefix" => &company_prefix }).await?;
+
+        let product_code = next_product_code(&company_prefix, code_length, &existing_codes).ok_or_else(|| {
+            ApiError::invalid_input("No free product codes left in the company prefix")
+        })?;
+
+        match insert_product(conn, tenant, &product_code, request).await {
+            Ok(_) => return Ok(product_code),
+            Err(Error::Server(ref server_error)) if server_error.code == ER_DUP_ENTRY => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(ApiError::Conflict("Failed to reserve a free product code".to_string()))
+}
+
+impl Database {
+    //get single product by product code
+    pub async fn get_product_by_code(&self, tenant: &Tenant, product_code: &str) -> Result<Option<Products>, Error> {
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => product_code,
+        };
+
+        let mut conn = self.conn().await?;
+        let product: Option<Products> = conn.exec_first(product_query("AND p.product_code = :product_code"), named_params).await?;
+
+        Ok(product)
+    }
+
+    //resolve a scanned GTIN-14 to the product, whichever GTIN length the product_code is stored in
+    pub async fn get_product_by_gtin(&self, tenant: &Tenant, gtin: &str) -> Result<Option<Products>, Error> {
+        let candidates = gtin_candidates(gtin);
+        let placeholders: Vec<String> = (0..candidates.len()).map(|i| format!(":gtin{}", i)).collect();
+        let query = product_query(&format!("AND p.product_code IN ({}) LIMIT 1", placeholders.join(", ")));
+
+        let mut named_params: HashMap<Vec<u8>, Value> = HashMap::from([(b"tenant_id".to_vec(), Value::from(tenant.id()))]);
+        for (i, candidate) in candidates.into_iter().enumerate() {
+            named_params.insert(format!("gtin{}", i).into_bytes(), Value::from(candidate));
+        }
+
+        let mut conn = self.conn().await?;
+        let product: Option<Products> = conn.exec_first(query, Params::Named(named_params)).await?;
+
+        Ok(product)
+    }
+
+    //CREATE new product variant, generating the next free product_code in the company prefix if none is given
+    pub async fn create_product(&self, tenant: &Tenant, request: &AddProductRequest, config: &ProductsConfig) -> Result<Products, ApiError> {
+        let attributes = request.variant_attributes();
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        if let Some(family_code) = &request.family_code {
+            let family: Option<String> = tx.exec_first(
+                "SELECT family_code FROM product_families WHERE tenant_id = :tenant_id AND family_code = :family_code FOR UPDATE",
+                params! { "tenant_id" => tenant.id(), "family_code" => family_code },
+            ).await?;
+            if family.is_none() {
+                return Err(ApiError::NotFound("Product family not found".to_string()));
+            }
+
+            check_variant_is_new(&mut tx, tenant, family_code, &attributes, None).await?;
+        }
+
+        let product_code = match &request.product_code {
+            Some(product_code) => {
+                insert_product(&mut tx, tenant, product_code, request).await?;
+                product_code.clone()
+            }
+            None => insert_generated_product(&mut tx, tenant, request, config).await?,
+        };
+
+        upsert_attributes(&mut tx, tenant, &product_code, &attributes).await?;
+        tx.commit().await?;
+
+        Ok(Products { product_code, product_name: request.product_name.clone(), family_code: request.family_code.clone(), attributes })
+    }
+
+    //set attributes of an existing product, the variant has to stay unique in its family
+    pub async fn set_product_attributes(&self, tenant: &Tenant, request: &SetProductAttributesRequest) -> Result<Products, ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let product: Option<Products> = tx.exec_first(
+            product_query("AND p.product_code = :product_code FOR UPDATE"),
+            params! { "tenant_id" => tenant.id(), "product_code" => &request.product_code },
+        ).await?;
+        let mut product = product.ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+        product.attributes.extend(request.attributes.clone());
+
+        if let Some(family_code) = &product.family_code {
+            // Same lock as create_product, so two variants can not both pass the check
+            tx.exec_drop(
+                "SELECT family_code FROM product_families WHERE tenant_id = :tenant_id AND family_code = :family_code FOR UPDATE",
+                params! { "tenant_id" => tenant.id(), "family_code" => family_code },
+            ).await?;
+            check_variant_is_new(&mut tx, tenant, family_code, &product.attributes, Some(&product.product_code)).await?;
+        }
+
+        upsert_attributes(&mut tx, tenant, &product.product_code, &request.attributes).await?;
+        tx.commit().await?;
+
+        Ok(product)
+    }
+
+    //CREATE new product family
+    pub async fn create_product_family(&self, tenant: &Tenant, request: &CreateProductFamilyRequest) -> Result<ProductFamily, Error> {
+        let mut conn = self.conn().await?;
+        conn.exec_drop(
+            "INSERT INTO product_families (tenant_id, family_code, name) VALUES (:tenant_id, :family_code, :name)",
+            params! { "tenant_id" => tenant.id(), "family_code" => &request.family_code, "name" => &request.name },
+        ).await?;
+
+        Ok(ProductFamily { family_code: request.family_code.clone(), name: request.name.clone() })
+    }
+
+    //get product family with all its variants
+    pub async fn get_product_family(&self, tenant: &Tenant, family_code: &str) -> Result<Option<ProductFamilyDetails>, Error> {
+        let mut conn = self.conn().await?;
+
+        let family: Option<ProductFamily> = conn.exec_first(
+            "SELECT family_code, name FROM product_families WHERE tenant_id = :tenant_id AND family_code = :family_code",
+            params! { "tenant_id" => tenant.id(), "family_code" => family_code },
+        ).await?;
+
+        let Some(family) = family else {
+            return Ok(None);
+        };
+
+        let variants: Vec<Products> = conn.exec(
+            product_query("AND p.family_code = :family_code ORDER BY p.product_code"),
+            params! { "tenant_id" => tenant.id(), "family_code" => family_code },
+        ).await?;
+
+        Ok(Some(ProductFamilyDetails { family, variants }))
+    }
+}
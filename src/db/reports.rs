@@ -0,0 +1,43 @@
+use mysql_async::{prelude::Queryable, Error, params};
+
+use crate::db::database::Database;
+use crate::db::products::attributes_json;
+use crate::models::reports::{SlowMovingQuery, SlowMovingStock};
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //stock rows with no outbound movement in the last N days, longest idle first
+    pub async fn get_slow_moving_stock(&self, tenant: &Tenant, request: &SlowMovingQuery) -> Result<Vec<SlowMovingStock>, Error> {
+        // Rows that never shipped anything are idle since they were created
+        let query = format!(
+            "SELECT u.product_code, p.product_name, {} AS attributes, u.warehouse, u.location, u.lot_number, u.pcs, u.last_movement_at, u.last_outbound_at,
+                    CAST(DATEDIFF(NOW(), COALESCE(u.last_outbound_at, u.created_at)) AS SIGNED) AS days_idle
+             FROM unique_identifiers u
+             JOIN products p ON p.tenant_id = u.tenant_id AND p.product_code = u.product_code
+             WHERE u.tenant_id = :tenant_id AND u.pcs > 0
+               AND COALESCE(u.last_outbound_at, u.created_at) < DATE_SUB(NOW(), INTERVAL :days DAY)
+               AND (:warehouse IS NULL OR u.warehouse = :warehouse)
+             ORDER BY days_idle DESC, u.warehouse, u.location",
+            attributes_json("p"),
+        );
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "days" => request.days,
+            "warehouse" => &request.warehouse,
+        };
+
+        let mut conn = self.conn().await?;
+
+        conn.exec(query, named_params).await
+    }
+
+    //total pcs per warehouse for the wms_stock_pcs gauge
+    pub async fn get_stock_per_warehouse(&self, tenant: &Tenant) -> Result<Vec<(String, i64)>, Error> {
+        let query = "SELECT warehouse, CAST(SUM(pcs) AS SIGNED) FROM unique_identifiers WHERE tenant_id = :tenant_id GROUP BY warehouse ORDER BY warehouse";
+
+        let mut conn = self.conn().await?;
+
+        conn.exec(query, params! { "tenant_id" => tenant.id() }).await
+    }
+}
@@ -0,0 +1,270 @@
+use mysql_async::{prelude::Queryable, Error, params, TxOpts};
+use rust_decimal::Decimal;
+
+use crate::db::database::{add_pcs, build_concatenated_string, Database, StockMovements};
+use crate::models::errors::ApiError;
+use crate::db::serials::{check_serials, is_serial_tracked, receive_serials};
+use crate::db::valuation::record_receipt;
+use crate::models::incoming::UniqueIdentifier;
+use crate::models::returns::{
+    CreateReturnAuthorizationRequest, Disposition, DispositionRequest, GetReturnAuthorization, ReceiveReturnRequest,
+    ReturnAuthorization, ReturnAuthorizationDetails, ReturnLineStatus, ReturnReceipt,
+};
+use crate::models::stock_status::StockStatus;
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //CREATE return authorization for a sales order
+    pub async fn create_return_authorization(&self, tenant: &Tenant, request: &CreateReturnAuthorizationRequest) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "INSERT INTO return_authorizations (tenant_id, rma_number, sales_order_number, customer) VALUES (:tenant_id, :rma_number, :sales_order_number, :customer)",
+            params! {
+                "tenant_id" => tenant.id(),
+                "rma_number" => &request.rma_number,
+                "sales_order_number" => &request.sales_order_number,
+                "customer" => &request.customer,
+            },
+        ).await?;
+
+        // The same product listed twice is authorized once with the summed quantity and the
+        // pcs weighted unit cost, unit_cost is updated first while pcs_authorized is the old one
+        for line in &request.lines {
+            tx.exec_drop(
+                "INSERT INTO return_authorization_lines (tenant_id, rma_number, product_code, pcs_authorized, unit_cost) VALUES (:tenant_id, :rma_number, :product_code, :pcs, :unit_cost)
+                 ON DUPLICATE KEY UPDATE
+                    unit_cost = CASE
+                        WHEN unit_cost IS NULL THEN VALUES(unit_cost)
+                        WHEN VALUES(unit_cost) IS NULL THEN unit_cost
+                        ELSE (unit_cost * pcs_authorized + VALUES(unit_cost) * VALUES(pcs_authorized)) / (pcs_authorized + VALUES(pcs_authorized))
+                    END,
+                    pcs_authorized = pcs_authorized + VALUES(pcs_authorized)",
+                params! {
+                    "tenant_id" => tenant.id(),
+                    "rma_number" => &request.rma_number,
+                    "product_code" => &line.product_code,
+                    "pcs" => line.pcs,
+                    "unit_cost" => line.unit_cost,
+                },
+            ).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    //get RMA with authorized lines and receipts
+    pub async fn get_return_authorization(&self, tenant: &Tenant, request: &GetReturnAuthorization) -> Result<Option<ReturnAuthorizationDetails>, Error> {
+        let named_params = params! { "tenant_id" => tenant.id(), "rma_number" => &request.rma_number };
+        let mut conn = self.conn().await?;
+
+        let authorization: Option<ReturnAuthorization> = conn.exec_first(
+            "SELECT rma_number, sales_order_number, customer, created_at FROM return_authorizations WHERE tenant_id = :tenant_id AND rma_number = :rma_number",
+            named_params.clone(),
+        ).await?;
+
+        let authorization = match authorization {
+            Some(authorization) => authorization,
+            None => return Ok(None),
+        };
+
+        let lines: Vec<ReturnLineStatus> = conn.exec(
+            "SELECT product_code, pcs_authorized, pcs_received FROM return_authorization_lines WHERE tenant_id = :tenant_id AND rma_number = :rma_number ORDER BY product_code",
+            named_params.clone(),
+        ).await?;
+        let receipts: Vec<ReturnReceipt> = conn.exec(
+            "SELECT id, product_code, warehouse, pcs, item_condition, reason, lot_number, expiry_date, received_at, disposition, disposition_warehouse, disposition_location
+             FROM return_receipts WHERE tenant_id = :tenant_id AND rma_number = :rma_number ORDER BY id",
+            named_params,
+        ).await?;
+
+        let status = return_status(&lines, &receipts).to_string();
+
+        Ok(Some(ReturnAuthorizationDetails { authorization, status, lines, receipts }))
+    }
+
+    //RECEIVE returned goods, they wait at receiving until a disposition is posted
+    pub async fn receive_return(&self, tenant: &Tenant, request: &ReceiveReturnRequest) -> Result<u64, ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let line: Option<(i32, i32)> = tx.exec_first(
+            "SELECT pcs_authorized, pcs_received FROM return_authorization_lines WHERE tenant_id = :tenant_id AND rma_number = :rma_number AND product_code = :product_code FOR UPDATE",
+            params! { "tenant_id" => tenant.id(), "rma_number" => &request.rma_number, "product_code" => &request.product_code },
+        ).await?;
+
+        let (pcs_authorized, pcs_received) = line.ok_or_else(|| ApiError::NotFound("Product is not authorized for return on this RMA".to_string()))?;
+        if pcs_received + request.pcs > pcs_authorized {
+            return Err(ApiError::invalid_input("Returned pcs exceed the authorized quantity"));
+        }
+
+        let serial_tracked = is_serial_tracked(&mut tx, tenant, &request.product_code).await?;
+        check_serials(serial_tracked, request.serial_numbers.as_ref(), request.pcs)?;
+
+        tx.exec_drop(
+            "INSERT INTO return_receipts (tenant_id, rma_number, product_code, warehouse, pcs, item_condition, reason, lot_number, expiry_date)
+             VALUES (:tenant_id, :rma_number, :product_code, :warehouse, :pcs, :item_condition, :reason, :lot_number, :expiry_date)",
+            params! {
+                "tenant_id" => tenant.id(),
+                "rma_number" => &request.rma_number,
+                "product_code" => &request.product_code,
+                "warehouse" => &request.warehouse,
+                "pcs" => request.pcs,
+                "item_condition" => request.condition.as_db_str(),
+                "reason" => &request.reason,
+                "lot_number" => &request.lot_number,
+                "expiry_date" => request.expiry_date,
+            },
+        ).await?;
+        let receipt_id = tx.last_insert_id().unwrap_or_default();
+
+        for serial_number in request.serial_numbers.iter().flatten() {
+            tx.exec_drop(
+                "INSERT INTO return_receipt_serials (tenant_id, receipt_id, serial_number) VALUES (:tenant_id, :receipt_id, :serial_number)",
+                params! { "tenant_id" => tenant.id(), "receipt_id" => receipt_id, "serial_number" => serial_number },
+            ).await?;
+        }
+
+        tx.exec_drop(
+            "UPDATE return_authorization_lines SET pcs_received = pcs_received + :pcs WHERE tenant_id = :tenant_id AND rma_number = :rma_number AND product_code = :product_code",
+            params! { "tenant_id" => tenant.id(), "pcs" => request.pcs, "rma_number" => &request.rma_number, "product_code" => &request.product_code },
+        ).await?;
+
+        tx.commit().await?;
+
+        Ok(receipt_id)
+    }
+
+    //warehouses the receipts were received in, unknown receipts and receipts without a warehouse are left out
+    pub async fn receipt_warehouses(&self, tenant: &Tenant, receipt_ids: &[u64]) -> Result<Vec<String>, Error> {
+        let mut conn = self.conn().await?;
+        let mut warehouses = Vec::new();
+
+        for receipt_id in receipt_ids {
+            let warehouse: Option<Option<String>> = conn.exec_first(
+                "SELECT warehouse FROM return_receipts WHERE tenant_id = :tenant_id AND id = :id",
+                params! { "tenant_id" => tenant.id(), "id" => receipt_id },
+            ).await?;
+            warehouses.extend(warehouse.flatten());
+        }
+
+        Ok(warehouses)
+    }
+
+    //POST disposition of a receipt: restock as available, put into quarantine, or scrap without stock
+    pub async fn dispose_return(&self, tenant: &Tenant, request: &DispositionRequest) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let receipt: Option<ReturnReceipt> = tx.exec_first(
+            "SELECT id, product_code, warehouse, pcs, item_condition, reason, lot_number, expiry_date, received_at, disposition, disposition_warehouse, disposition_location
+             FROM return_receipts WHERE tenant_id = :tenant_id AND id = :id FOR UPDATE",
+            params! { "tenant_id" => tenant.id(), "id" => request.receipt_id },
+        ).await?;
+
+        let receipt = receipt.ok_or_else(|| ApiError::NotFound("Return receipt not found".to_string()))?;
+        if receipt.disposition.is_some() {
+            return Err(ApiError::Conflict("Disposition was already posted for this receipt".to_string()));
+        }
+
+        let serial_numbers: Vec<String> = tx.exec(
+            "SELECT serial_number FROM return_receipt_serials WHERE tenant_id = :tenant_id AND receipt_id = :receipt_id",
+            params! { "tenant_id" => tenant.id(), "receipt_id" => request.receipt_id },
+        ).await?;
+
+        let status = match request.disposition {
+            Disposition::Restock => Some(StockStatus::Available),
+            Disposition::Quarantine => Some(StockStatus::Quarantine),
+            Disposition::Scrap => None,
+        };
+
+        let mut movements = StockMovements::default();
+        match (status, &request.warehouse, &request.location) {
+            (Some(status), Some(warehouse), Some(location)) => {
+                let product_name: Option<String> = tx.exec_first(
+                    "SELECT product_name FROM products WHERE tenant_id = :tenant_id AND product_code = :product_code",
+                    params! { "tenant_id" => tenant.id(), "product_code" => &receipt.product_code },
+                ).await?;
+                let product_name = product_name.ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+                let row = UniqueIdentifier {
+                    concatenated_string: build_concatenated_string(
+                        &receipt.product_code, warehouse, location,
+                        receipt.lot_number.as_deref(), receipt.expiry_date, status,
+                    ),
+                    product_code: receipt.product_code.clone(),
+                    product_name,
+                    attributes: Default::default(),
+                    warehouse: warehouse.clone(),
+                    location: location.clone(),
+                    pcs: receipt.pcs,
+                    lot_number: receipt.lot_number.clone(),
+                    expiry_date: receipt.expiry_date,
+                    status,
+                    emptied_at: None,
+                    breakdown: None,
+                };
+                // Returns come back at the cost they were sold at when the RMA line has it,
+                // otherwise record_receipt falls back to the last known cost
+                let unit_cost: Option<Option<Decimal>> = tx.exec_first(
+                    "SELECT l.unit_cost FROM return_receipts r
+                     JOIN return_authorization_lines l ON l.tenant_id = r.tenant_id AND l.rma_number = r.rma_number AND l.product_code = r.product_code
+                     WHERE r.tenant_id = :tenant_id AND r.id = :id",
+                    params! { "tenant_id" => tenant.id(), "id" => request.receipt_id },
+                ).await?;
+
+                add_pcs(&mut tx, tenant, &row, receipt.pcs, &mut movements).await?;
+                record_receipt(&mut tx, tenant, &receipt.product_code, warehouse, receipt.pcs, unit_cost.flatten()).await?;
+
+                if !serial_numbers.is_empty() {
+                    receive_serials(&mut tx, tenant, &receipt.product_code, warehouse, location, &serial_numbers).await?;
+                }
+            }
+            (Some(_), _, _) => return Err(ApiError::invalid_input("Warehouse and location are required for restock and quarantine")),
+            (None, _, _) => {
+                for serial_number in &serial_numbers {
+                    tx.exec_drop(
+                        "UPDATE serial_numbers SET status = 'SCRAPPED', warehouse = NULL, location = NULL WHERE tenant_id = :tenant_id AND serial_number = :serial_number",
+                        params! { "tenant_id" => tenant.id(), "serial_number" => serial_number },
+                    ).await?;
+                }
+            }
+        }
+
+        tx.exec_drop(
+            "UPDATE return_receipts SET disposition = :disposition, disposition_warehouse = :warehouse, disposition_location = :location, dispositioned_at = NOW()
+             WHERE tenant_id = :tenant_id AND id = :id",
+            params! {
+                "tenant_id" => tenant.id(),
+                "disposition" => request.disposition.as_db_str(),
+                "warehouse" => &request.warehouse,
+                "location" => &request.location,
+                "id" => request.receipt_id,
+            },
+        ).await?;
+
+        tx.commit().await?;
+        self.record_movements(tenant, movements);
+
+        Ok(())
+    }
+}
+
+// OPEN until something arrives, CLOSED once everything is received and dispositioned
+fn return_status(lines: &[ReturnLineStatus], receipts: &[ReturnReceipt]) -> &'static str {
+    let fully_received = lines.iter().all(|line| line.pcs_received >= line.pcs_authorized);
+    let all_dispositioned = receipts.iter().all(|receipt| receipt.disposition.is_some());
+
+    if receipts.is_empty() {
+        "OPEN"
+    } else if !fully_received {
+        "PARTIALLY_RECEIVED"
+    } else if !all_dispositioned {
+        "RECEIVED"
+    } else {
+        "CLOSED"
+    }
+}
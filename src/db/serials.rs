@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use mysql_async::{prelude::Queryable, Error, params};
+
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::serials::{GetSerialNumber, SerialMovement, SerialNumber, SerialNumberDetails, SetSerialTracking};
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //get current location and movement history of a serial number
+    pub async fn get_serial_number_details(&self, tenant: &Tenant, request: &GetSerialNumber) -> Result<Option<SerialNumberDetails>, Error> {
+        let query_serial = "SELECT serial_number, product_code, warehouse, location, status, customer FROM serial_numbers WHERE tenant_id = :tenant_id AND serial_number = :serial_number";
+        let query_history = "SELECT movement_type, warehouse, location, customer, moved_at FROM serial_movements WHERE tenant_id = :tenant_id AND serial_number = :serial_number ORDER BY moved_at ASC, id ASC";
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "serial_number" => &request.serial_number,
+        };
+
+        let mut conn = self.conn().await?;
+        let serial: Option<SerialNumber> = conn.exec_first(query_serial, named_params.clone()).await?;
+
+        match serial {
+            Some(serial) => {
+                let history: Vec<SerialMovement> = conn.exec(query_history, named_params).await?;
+                Ok(Some(SerialNumberDetails { serial, history }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    //turn serial tracking on or off for a product, returns false if the product does not exist
+    pub async fn set_serial_tracking(&self, tenant: &Tenant, request: &SetSerialTracking) -> Result<bool, Error> {
+        let query = "UPDATE products SET serial_tracked = :serial_tracked WHERE tenant_id = :tenant_id AND product_code = :product_code";
+
+        let named_params = params! {
+            "serial_tracked" => request.serial_tracked,
+            "tenant_id" => tenant.id(),
+            "product_code" => &request.product_code,
+        };
+
+        let mut conn = self.conn().await?;
+        conn.exec_drop(query, named_params).await?;
+
+        Ok(conn.affected_rows() > 0)
+    }
+}
+
+pub(crate) async fn is_serial_tracked<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str) -> Result<bool, Error> {
+    let query = "SELECT serial_tracked FROM products WHERE tenant_id = :tenant_id AND product_code = :product_code";
+    let tracked: Option<bool> = conn.exec_first(query, params! { "tenant_id" => tenant.id(), "product_code" => product_code }).await?;
+
+    Ok(tracked.unwrap_or(false))
+}
+
+// Serial tracked products need exactly one distinct serial per piece,
+// other products must not carry serials at all.
+pub(crate) fn check_serials(serial_tracked: bool, serial_numbers: Option<&Vec<String>>, pcs: i32) -> Result<(), ApiError> {
+    match (serial_tracked, serial_numbers) {
+        (false, None) => Ok(()),
+        (false, Some(_)) => Err(ApiError::invalid_input("Product is not serial tracked")),
+        (true, None) => Err(ApiError::invalid_input("Serial numbers are required for serial tracked products")),
+        (true, Some(serials)) => {
+            let distinct: HashSet<&str> = serials.iter().map(|serial| serial.trim()).collect();
+
+            if distinct.contains("") {
+                return Err(ApiError::invalid_input("Serial number can not be empty"));
+            }
+            if distinct.len() != serials.len() {
+                return Err(ApiError::invalid_input("Serial numbers must be unique"));
+            }
+            if serials.len() != pcs as usize {
+                return Err(ApiError::invalid_input("Number of serial numbers must match pcs"));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+//register received serials at a location and record the movement
+pub(crate) async fn receive_serials<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, warehouse: &str, location: &str, serial_numbers: &[String]) -> Result<(), ApiError> {
+    let check_query = "SELECT status FROM serial_numbers WHERE tenant_id = :tenant_id AND serial_number = :serial_number";
+    let upsert_query = "INSERT INTO serial_numbers (tenant_id, serial_number, product_code, warehouse, location, status, customer) VALUES (:tenant_id, :serial_number, :product_code, :warehouse, :location, 'IN_STOCK', NULL)
+                        ON DUPLICATE KEY UPDATE warehouse = VALUES(warehouse), location = VALUES(location), status = 'IN_STOCK', customer = NULL";
+    let movement_query = "INSERT INTO serial_movements (tenant_id, serial_number, movement_type, warehouse, location, customer) VALUES (:tenant_id, :serial_number, 'RECEIVED', :warehouse, :location, NULL)";
+
+    for serial_number in serial_numbers {
+        let status: Option<String> = conn.exec_first(check_query, params! { "tenant_id" => tenant.id(), "serial_number" => serial_number }).await?;
+
+        if status.as_deref() == Some("IN_STOCK") {
+            return Err(ApiError::Conflict("Serial number is already in stock".to_string()));
+        }
+
+        conn.exec_drop(upsert_query, params! {
+            "tenant_id" => tenant.id(),
+            "serial_number" => serial_number,
+            "product_code" => product_code,
+            "warehouse" => warehouse,
+            "location" => location,
+        }).await?;
+
+        conn.exec_drop(movement_query, params! {
+            "tenant_id" => tenant.id(),
+            "serial_number" => serial_number,
+            "warehouse" => warehouse,
+            "location" => location,
+        }).await?;
+    }
+
+    Ok(())
+}
+
+//take serials out of a location, mark them shipped and record the movement
+pub(crate) async fn ship_serials<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, warehouse: &str, location: &str, serial_numbers: &[String], customer: Option<&str>) -> Result<(), ApiError> {
+    let check_query = "SELECT COUNT(*) FROM serial_numbers
+                       WHERE tenant_id = :tenant_id AND serial_number = :serial_number AND product_code = :product_code
+                       AND warehouse = :warehouse AND location = :location AND status = 'IN_STOCK'";
+    let update_query = "UPDATE serial_numbers SET status = 'SHIPPED', warehouse = NULL, location = NULL, customer = :customer WHERE tenant_id = :tenant_id AND serial_number = :serial_number";
+    let movement_query = "INSERT INTO serial_movements (tenant_id, serial_number, movement_type, warehouse, location, customer) VALUES (:tenant_id, :serial_number, 'SHIPPED', :warehouse, :location, :customer)";
+
+    for serial_number in serial_numbers {
+        let found: Option<i64> = conn.exec_first(check_query, params! {
+            "tenant_id" => tenant.id(),
+            "serial_number" => serial_number,
+            "product_code" => product_code,
+            "warehouse" => warehouse,
+            "location" => location,
+        }).await?;
+
+        if found.unwrap_or(0) == 0 {
+            return Err(ApiError::NotFound("Serial number not found at the location".to_string()));
+        }
+
+        conn.exec_drop(update_query, params! {
+            "tenant_id" => tenant.id(),
+            "customer" => customer,
+            "serial_number" => serial_number,
+        }).await?;
+
+        conn.exec_drop(movement_query, params! {
+            "tenant_id" => tenant.id(),
+            "serial_number" => serial_number,
+            "warehouse" => warehouse,
+            "location" => location,
+            "customer" => customer,
+        }).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_serials_requires_one_distinct_serial_per_piece() {
+        let serials = vec!["SN-1".to_string(), "SN-2".to_string()];
+        let duplicated = vec!["SN-1".to_string(), "SN-1".to_string()];
+
+        assert!(check_serials(false, None, 2).is_ok());
+        assert!(check_serials(false, Some(&serials), 2).is_err());
+        assert!(check_serials(true, None, 2).is_err());
+        assert!(check_serials(true, Some(&serials), 2).is_ok());
+        assert!(check_serials(true, Some(&serials), 3).is_err());
+        assert!(check_serials(true, Some(&duplicated), 2).is_err());
+    }
+}
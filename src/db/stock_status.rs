@@ -0,0 +1,73 @@
+use mysql_async::{prelude::Queryable, Error, params, TxOpts};
+
+use crate::db::database::{add_pcs, build_concatenated_string, deduct_pcs, select_stock, Database, StockMovements};
+use crate::models::errors::ApiError;
+use crate::db::products::{attributes_json, resolve_product_code};
+use crate::db::handling_units::packed_pcs;
+use crate::models::handling_units::loose_pcs;
+use crate::models::incoming::UniqueIdentifier;
+use crate::models::stock_status::{AvailableStock, ChangeStockStatusRequest};
+use crate::models::tenant::Tenant;
+
+impl Database {
+    //CHANGE status of a quantity, the pcs move to the row of the same stock with the new status
+    pub async fn change_stock_status(&self, tenant: &Tenant, request: &ChangeStockStatusRequest) -> Result<(), ApiError> {
+        if request.from_status == request.to_status {
+            return Err(ApiError::invalid_input("Stock already has the requested status"));
+        }
+
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let product_code = resolve_product_code(&mut tx, tenant, &request.product).await?;
+        let source_string = build_concatenated_string(
+            &product_code, &request.warehouse, &request.location,
+            request.lot_number.as_deref(), request.expiry_date, request.from_status,
+        );
+
+        let source: UniqueIdentifier = tx.exec_first(
+            select_stock("AND u.concatenated_string = :concatenated_string FOR UPDATE"),
+            params! { "tenant_id" => tenant.id(), "concatenated_string" => &source_string },
+        ).await?.ok_or_else(|| ApiError::NotFound("Concatenated string not found".to_string()))?;
+
+        // Pcs packed in handling units keep their status until they are unpacked
+        if request.pcs > loose_pcs(source.pcs, packed_pcs(&mut tx, tenant, &source_string).await?) {
+            return Err(ApiError::InsufficientStock("Not enough loose pcs for status change".to_string()));
+        }
+
+        let mut movements = StockMovements::default();
+        deduct_pcs(&mut tx, tenant, &source, request.pcs, self.empty_rows, &mut movements).await?;
+
+        let target = UniqueIdentifier {
+            concatenated_string: build_concatenated_string(
+                &product_code, &request.warehouse, &request.location,
+                request.lot_number.as_deref(), request.expiry_date, request.to_status,
+            ),
+            status: request.to_status,
+            ..source
+        };
+        add_pcs(&mut tx, tenant, &target, request.pcs, &mut movements).await?;
+
+        tx.commit().await?;
+        self.record_movements(tenant, movements);
+
+        Ok(())
+    }
+
+    //available quantity per product, quarantined, blocked and damaged stock is not counted
+    pub async fn get_available_stock(&self, tenant: &Tenant) -> Result<Vec<AvailableStock>, Error> {
+        let query = format!(
+            "SELECT u.product_code, p.product_name, {} AS attributes, CAST(SUM(u.pcs) AS SIGNED) FROM unique_identifiers u
+             JOIN products p ON p.tenant_id = u.tenant_id AND p.product_code = u.product_code
+             WHERE u.tenant_id = :tenant_id AND u.status = 'AVAILABLE' AND u.pcs > 0
+             GROUP BY u.product_code, p.product_name
+             ORDER BY p.product_name, u.product_code",
+            attributes_json("p"),
+        );
+
+        let mut conn = self.conn().await?;
+        let available: Vec<AvailableStock> = conn.exec(query, params! { "tenant_id" => tenant.id() }).await?;
+
+        Ok(available)
+    }
+}
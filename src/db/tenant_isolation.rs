@@ -0,0 +1,348 @@
+// Every statement in Database has to filter or write tenant_id once for each
+// table it touches, otherwise one company could read or change rows of another.
+// The source check is a quick lint without a database, the live test below is
+// the one that proves two tenants can not reach each other's rows.
+
+const SOURCES: &[(&str, &str)] = &[
+    ("database.rs", include_str!("database.rs")),
+    ("lots.rs", include_str!("lots.rs")),
+    ("serials.rs", include_str!("serials.rs")),
+    ("products.rs", include_str!("products.rs")),
+    ("packaging.rs", include_str!("packaging.rs")),
+    ("handling_units.rs", include_str!("handling_units.rs")),
+    ("stock_status.rs", include_str!("stock_status.rs")),
+    ("returns.rs", include_str!("returns.rs")),
+    ("valuation.rs", include_str!("valuation.rs")),
+    ("reports.rs", include_str!("reports.rs")),
+    ("abc.rs", include_str!("abc.rs")),
+    ("empty_stock.rs", include_str!("empty_stock.rs")),
+    ("pool.rs", include_str!("pool.rs")),
+    ("health.rs", include_str!("health.rs")),
+    ("users.rs", include_str!("users.rs")),
+];
+
+// Tables shared by all tenants
+const GLOBAL_TABLES: &[&str] = &["SCHEMA_VERSION"];
+
+// Lookups that find the tenant in the first place, so they can not be scoped to one.
+// Each entry is the file and the start of the statement, with the reason it is safe
+const CROSS_TENANT_LOOKUPS: &[(&str, &str, &str)] = &[
+    (
+        "users.rs",
+        "SELECT tenant_id, key_hash, role, all_warehouses FROM api_keys WHERE key_id = :key_id",
+        "the API key id is globally unique and the key decides the tenant of the request",
+    ),
+];
+
+fn is_cross_tenant_lookup(file: &str, statement: &str) -> bool {
+    CROSS_TENANT_LOOKUPS.iter().any(|(lookup_file, start, _)| *lookup_file == file && statement.trim_start().starts_with(start))
+}
+
+//string literals of a source file, commented out lines are skipped
+fn string_literals(source: &str) -> Vec<String> {
+    let code: String = source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut literals = Vec::new();
+    let mut chars = code.chars();
+    let mut current: Option<String> = None;
+
+    while let Some(c) = chars.next() {
+        match (&mut current, c) {
+            (None, '"') => current = Some(String::new()),
+            (None, _) => {}
+            (Some(literal), '\\') => {
+                literal.push(c);
+                literal.extend(chars.next());
+            }
+            (Some(_), '"') => literals.extend(current.take()),
+            (Some(literal), _) => literal.push(c),
+        }
+    }
+
+    literals
+}
+
+//subqueries built on their own start with a parenthesis
+fn is_statement(literal: &str) -> bool {
+    let upper = literal.trim_start().trim_start_matches('(').to_uppercase();
+    ["SELECT ", "INSERT ", "UPDATE ", "DELETE "].iter().any(|keyword| upper.starts_with(keyword))
+}
+
+//tables named after FROM, JOIN, INTO and UPDATE, subqueries and global tables are not counted
+fn table_references(statement: &str) -> usize {
+    let words: Vec<String> = statement.split_whitespace().map(|word| word.to_uppercase()).collect();
+
+    (0..words.len().saturating_sub(1))
+        .filter(|&i| matches!(words[i].as_str(), "FROM" | "JOIN" | "INTO" | "UPDATE"))
+        .filter(|&i| !words[i + 1].starts_with('('))
+        .filter(|&i| !GLOBAL_TABLES.contains(&words[i + 1].as_str()))
+        .filter(|&i| !(words[i] == "UPDATE" && i > 0 && words[i - 1] == "KEY"))
+        .count()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Clause {
+    Select,
+    From,
+    Condition,
+    InsertColumns,
+    Other,
+}
+
+//words and parentheses of a statement, commas and comparison operators split words
+fn tokens(statement: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+
+    for c in statement.chars() {
+        if c.is_whitespace() || matches!(c, ',' | '=' | '<' | '>' | '!' | '(' | ')') {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            if matches!(c, '(' | ')') {
+                tokens.push(c.to_string());
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    tokens.extend((!word.is_empty()).then_some(word));
+
+    tokens
+}
+
+//tenant_id columns in WHERE and ON conditions and in INSERT column lists. Selected or
+//grouped tenant_id columns and the :tenant_id parameter on its own do not scope anything
+fn tenant_columns(statement: &str) -> usize {
+    let tokens = tokens(statement);
+    // Every parenthesis keeps its own clause, a subquery in the SELECT list does not end the outer one
+    let mut clauses = vec![Clause::Other];
+    let mut count = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let upper = token.to_uppercase();
+        let next = tokens.get(i + 1).map(|next| next.to_uppercase());
+
+        match upper.as_str() {
+            "(" => {
+                let insert_columns = i >= 2 && tokens[i - 2].eq_ignore_ascii_case("INTO");
+                let inherited = *clauses.last().unwrap_or(&Clause::Other);
+                clauses.push(if insert_columns { Clause::InsertColumns } else { inherited });
+                continue;
+            }
+            ")" => {
+                if clauses.len() > 1 {
+                    clauses.pop();
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let clause = match upper.as_str() {
+            "SELECT" => Some(Clause::Select),
+            "FROM" | "JOIN" | "UPDATE" => Some(Clause::From),
+            "WHERE" => Some(Clause::Condition),
+            "ON" if next.as_deref() == Some("DUPLICATE") => Some(Clause::Other),
+            "ON" => Some(Clause::Condition),
+            "SET" | "VALUES" | "GROUP" | "ORDER" | "HAVING" | "LIMIT" | "UNION" => Some(Clause::Other),
+            _ => None,
+        };
+        if let (Some(clause), Some(current)) = (clause, clauses.last_mut()) {
+            *current = clause;
+            continue;
+        }
+
+        let is_column = token == "tenant_id" || token.ends_with(".tenant_id");
+        let counted = matches!(clauses.last(), Some(Clause::Condition | Clause::InsertColumns));
+        if is_column && counted {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+#[test]
+fn every_database_query_is_scoped_to_the_tenant() {
+    let mut statements = 0;
+
+    for (file, source) in SOURCES {
+        for statement in string_literals(source).into_iter().filter(|literal| is_statement(literal)) {
+            statements += 1;
+
+            if is_cross_tenant_lookup(file, &statement) {
+                continue;
+            }
+
+            let tables = table_references(&statement);
+            let scoped = tenant_columns(&statement);
+
+            assert!(
+                scoped >= tables,
+                "{}: query touches {} table(s) but has {} tenant_id condition(s): {}",
+                file, tables, scoped, statement,
+            );
+        }
+    }
+
+    assert!(statements > 50, "expected to find the queries of Database, found {}", statements);
+}
+
+#[test]
+fn every_cross_tenant_lookup_exists() {
+    for (file, start, _) in CROSS_TENANT_LOOKUPS {
+        let source = SOURCES.iter().find(|(name, _)| name == file).map(|(_, source)| *source).unwrap_or_default();
+        assert!(
+            string_literals(source).iter().any(|statement| statement.trim_start().starts_with(start)),
+            "{}: allowed cross-tenant lookup is gone, remove it from the list: {}", file, start,
+        );
+    }
+}
+
+#[test]
+fn every_database_module_is_checked() {
+    for module in include_str!("mod.rs").lines().filter_map(|line| line.trim().strip_prefix("pub mod ")) {
+        let file = format!("{}.rs", module.trim_end_matches(';'));
+        assert!(SOURCES.iter().any(|(name, _)| *name == file), "{} is missing from the tenant isolation check", file);
+    }
+}
+
+#[test]
+fn unscoped_queries_are_detected() {
+    let source = r#"let query = "SELECT * FROM unique_identifiers u JOIN products p ON p.product_code = u.product_code WHERE u.tenant_id = :tenant_id";"#;
+    let statement = &string_literals(source)[0];
+
+    assert!(is_statement(statement));
+    assert_eq!(table_references(statement), 2);
+    assert_eq!(tenant_columns(statement), 1);
+
+    let upsert = "INSERT INTO handling_unit_contents (tenant_id, lpn) VALUES (:tenant_id, :lpn) ON DUPLICATE KEY UPDATE pcs = pcs + VALUES(pcs)";
+    assert_eq!(table_references(upsert), 1);
+    assert_eq!(tenant_columns(upsert), 1);
+
+    // Selecting or grouping by tenant_id does not scope the query
+    let selected = "SELECT tenant_id, key_hash FROM api_keys WHERE key_id = :key_id GROUP BY tenant_id";
+    assert_eq!(tenant_columns(selected), 0);
+    assert!(!is_cross_tenant_lookup("products.rs", selected));
+
+    let subquery = "SELECT p.product_code, (SELECT JSON_OBJECTAGG(a.attribute_name, a.attribute_value) FROM product_attributes a WHERE a.tenant_id = p.tenant_id) FROM products p WHERE p.tenant_id = :tenant_id";
+    assert_eq!(table_references(subquery), 2);
+    assert_eq!(tenant_columns(subquery), 3);
+
+    let copy = "INSERT INTO archive (tenant_id, lpn) SELECT tenant_id, lpn FROM handling_units WHERE tenant_id = :tenant_id";
+    assert_eq!(table_references(copy), 2);
+    assert_eq!(tenant_columns(copy), 2);
+}
+
+// The source scan misses SQL assembled at runtime, so the same product code is
+// stocked by two tenants on a live database and each must only reach its own rows
+mod live {
+    use std::env;
+
+    use dotenv::dotenv;
+    use mysql_async::prelude::Queryable;
+
+    use crate::config::DatabaseConfig;
+    use crate::db::database::Database;
+    use crate::models::errors::ApiError;
+    use crate::models::handling_units::{CreateHandlingUnitRequest, GetHandlingUnit, HandlingUnitType};
+    use crate::models::incoming::AddOrUpdateUniqueIdentifierRequest;
+    use crate::models::outgoing::RemoveUniqueIdentifierRequest;
+    use crate::models::packaging::Unit;
+    use crate::models::products::ProductRef;
+    use crate::models::stock_status::StockStatus;
+    use crate::models::tenant::Tenant;
+    use crate::models::valuation::ValuationQuery;
+
+    const PRODUCT_CODE: &str = "806807071423";
+    const WAREHOUSE: &str = "ISOLATION";
+
+    async fn setup_two_tenants() -> (Database, Tenant, Tenant) {
+        dotenv().ok();
+        let db_url = env::var("MYSQL_DB_URL").expect("MYSQL_DB_URL not set in .env file");
+        let db = Database::with_pool(mysql_async::Pool::new(db_url.as_str()), &DatabaseConfig::default());
+
+        let mut conn = db.conn().await.unwrap();
+        conn.query_drop(
+            "INSERT IGNORE INTO tenants (tenant_id, name) VALUES ('isolation-a', 'Isolation A'), ('isolation-b', 'Isolation B')"
+        ).await.unwrap();
+        conn.exec_drop(
+            "INSERT IGNORE INTO products (tenant_id, product_code, product_name) VALUES ('isolation-a', ?, 'Smart thermostat'), ('isolation-b', ?, 'Smart thermostat')",
+            (PRODUCT_CODE, PRODUCT_CODE),
+        ).await.unwrap();
+
+        (db, Tenant::new("isolation-a").unwrap(), Tenant::new("isolation-b").unwrap())
+    }
+
+    fn add_request(location: &str, pcs: i32) -> AddOrUpdateUniqueIdentifierRequest {
+        AddOrUpdateUniqueIdentifierRequest {
+            product: ProductRef::by_code(PRODUCT_CODE),
+            warehouse: WAREHOUSE.to_string(),
+            location: location.to_string(),
+            pcs,
+            unit: Unit::Pcs,
+            lot_number: None,
+            expiry_date: None,
+            status: StockStatus::Available,
+            serial_numbers: None,
+            unit_cost: None,
+        }
+    }
+
+    fn remove_request(location: &str, pcs: i32) -> RemoveUniqueIdentifierRequest {
+        RemoveUniqueIdentifierRequest {
+            product: ProductRef::by_code(PRODUCT_CODE),
+            warehouse: WAREHOUSE.to_string(),
+            location: location.to_string(),
+            pcs,
+            unit: Unit::Pcs,
+            lot_number: None,
+            expiry_date: None,
+            status: StockStatus::Available,
+            serial_numbers: None,
+            customer: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn tenants_only_reach_their_own_rows() {
+        let (db, tenant_a, tenant_b) = setup_two_tenants().await;
+        let suffix = chrono::Utc::now().timestamp_micros();
+        let location = format!("ISO-{}", suffix);
+        let lpn = format!("ISO-{}", suffix);
+
+        db.add_or_update_unique_identifier(&tenant_a, &add_request(&location, 5)).await.unwrap();
+        db.create_handling_unit(&tenant_a, &CreateHandlingUnitRequest {
+            lpn: lpn.clone(),
+            unit_type: HandlingUnitType::Pallet,
+            warehouse: WAREHOUSE.to_string(),
+            location: location.clone(),
+            parent_lpn: None,
+        }).await.unwrap();
+
+        let stock_of = |locations: Vec<crate::models::incoming::UniqueIdentifier>| {
+            locations.into_iter().filter(|row| row.location == location).map(|row| row.pcs).sum::<i32>()
+        };
+
+        // Tenant B sees neither the stock nor the handling unit of tenant A
+        assert_eq!(stock_of(db.get_all_locations(&tenant_b).await.unwrap()), 0);
+        assert!(db.get_handling_unit(&tenant_b, &GetHandlingUnit { lpn: lpn.clone() }).await.unwrap().is_none());
+        let valuation = db.get_valuation(&tenant_b, &ValuationQuery { date: None, warehouse: Some(WAREHOUSE.to_string()), product_code: None }).await.unwrap();
+        assert!(valuation.rows.is_empty(), "tenant B values stock of tenant A: {:?}", valuation.rows);
+
+        // Removing the same row as tenant B fails and leaves the stock of tenant A as it was
+        let removed = db.remove_unique_identifier(&tenant_b, &remove_request(&location, 1)).await;
+        assert!(matches!(removed, Err(ApiError::NotFound(_))), "tenant B removed stock of tenant A: {:?}", removed);
+        assert_eq!(stock_of(db.get_all_locations(&tenant_a).await.unwrap()), 5);
+
+        // Stock added by tenant B at the same location is a separate row
+        db.add_or_update_unique_identifier(&tenant_b, &add_request(&location, 2)).await.unwrap();
+        assert_eq!(stock_of(db.get_all_locations(&tenant_a).await.unwrap()), 5);
+        assert_eq!(stock_of(db.get_all_locations(&tenant_b).await.unwrap()), 2);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use mysql_async::{prelude::Queryable, Error, params, Transaction, TxOpts};
+
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+use crate::models::tenant::Tenant;
+use crate::models::users::{ApiKey, CreateApiKeyRequest, Role, User, WarehouseAccess};
+
+// Replaces the warehouses a user is limited to, all_warehouses is stored on the user itself
+async fn set_user_warehouses(tx: &mut Transaction<'_>, tenant: &Tenant, username: &str, warehouses: &[String]) -> Result<(), Error> {
+    tx.exec_drop(
+        "DELETE FROM user_warehouses WHERE tenant_id = :tenant_id AND username = :username",
+        params! { "tenant_id" => tenant.id(), "username" => username },
+    ).await?;
+
+    tx.exec_batch(
+        "INSERT INTO user_warehouses (tenant_id, username, warehouse) VALUES (:tenant_id, :username, :warehouse)",
+        warehouses.iter().map(|warehouse| params! { "tenant_id" => tenant.id(), "username" => username, "warehouse" => warehouse }),
+    ).await
+}
+
+impl Database {
+    //argon2 hash of the user's password, None for unknown users
+    pub async fn get_password_hash(&self, tenant: &Tenant, username: &str) -> Result<Option<String>, Error> {
+        let mut conn = self.conn().await?;
+        conn.exec_first(
+            "SELECT password_hash FROM users WHERE tenant_id = :tenant_id AND username = :username",
+            params! { "tenant_id" => tenant.id(), "username" => username },
+        ).await
+    }
+
+    //role and warehouses of a user, None for unknown users. Read on every request so changes apply right away
+    pub async fn get_user_access(&self, tenant: &Tenant, username: &str) -> Result<Option<(Role, WarehouseAccess)>, Error> {
+        let mut conn = self.conn().await?;
+        let user: Option<(String, bool)> = conn.exec_first(
+            "SELECT role, all_warehouses FROM users WHERE tenant_id = :tenant_id AND username = :username",
+            params! { "tenant_id" => tenant.id(), "username" => username },
+        ).await?;
+
+        let Some((role, all_warehouses)) = user else {
+            return Ok(None);
+        };
+
+        let warehouses: Vec<String> = conn.exec(
+            "SELECT warehouse FROM user_warehouses WHERE tenant_id = :tenant_id AND username = :username ORDER BY warehouse",
+            params! { "tenant_id" => tenant.id(), "username" => username },
+        ).await?;
+
+        Ok(Some((Role::parse(&role), WarehouseAccess { all_warehouses, warehouses })))
+    }
+
+    pub async fn get_users(&self, tenant: &Tenant) -> Result<Vec<User>, Error> {
+        let mut conn = self.conn().await?;
+        let mut users: Vec<User> = conn.exec(
+            "SELECT username, role, all_warehouses, created_at FROM users WHERE tenant_id = :tenant_id ORDER BY username",
+            params! { "tenant_id" => tenant.id() },
+        ).await?;
+
+        let assignments: Vec<(String, String)> = conn.exec(
+            "SELECT username, warehouse FROM user_warehouses WHERE tenant_id = :tenant_id ORDER BY warehouse",
+            params! { "tenant_id" => tenant.id() },
+        ).await?;
+
+        let mut warehouses: HashMap<String, Vec<String>> = HashMap::new();
+        for (username, warehouse) in assignments {
+            warehouses.entry(username).or_default().push(warehouse);
+        }
+        for user in &mut users {
+            user.access.warehouses = warehouses.remove(&user.username).unwrap_or_default();
+        }
+
+        Ok(users)
+    }
+
+    //password_hash is hashed by the caller, plain passwords never reach the database
+    pub async fn create_user(&self, tenant: &Tenant, username: &str, password_hash: &str, role: Role, access: &WarehouseAccess) -> Result<(), ApiError> {
+        if self.get_user_access(tenant, username).await?.is_some() {
+            return Err(ApiError::Conflict("Username already exists".to_string()));
+        }
+
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "INSERT INTO users (tenant_id, username, password_hash, role, all_warehouses) VALUES (:tenant_id, :username, :password_hash, :role, :all_warehouses)",
+            params! { "tenant_id" => tenant.id(), "username" => username, "password_hash" => password_hash, "role" => role.as_str(), "all_warehouses" => access.all_warehouses },
+        ).await?;
+        set_user_warehouses(&mut tx, tenant, username, &access.warehouses).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_user_access(&self, tenant: &Tenant, username: &str, role: Role, access: &WarehouseAccess) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let found: Option<u8> = tx.exec_first(
+            "SELECT 1 FROM users WHERE tenant_id = :tenant_id AND username = :username FOR UPDATE",
+            params! { "tenant_id" => tenant.id(), "username" => username },
+        ).await?;
+        if found.is_none() {
+            return Err(ApiError::NotFound("User not found".to_string()));
+        }
+
+        tx.exec_drop(
+            "UPDATE users SET role = :role, all_warehouses = :all_warehouses WHERE tenant_id = :tenant_id AND username = :username",
+            params! { "tenant_id" => tenant.id(), "username" => username, "role" => role.as_str(), "all_warehouses" => access.all_warehouses },
+        ).await?;
+        set_user_warehouses(&mut tx, tenant, username, &access.warehouses).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_user_password(&self, tenant: &Tenant, username: &str, password_hash: &str) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        conn.exec_drop(
+            "UPDATE users SET password_hash = :password_hash WHERE tenant_id = :tenant_id AND username = :username",
+            params! { "tenant_id" => tenant.id(), "username" => username, "password_hash" => password_hash },
+        ).await?;
+
+        match conn.affected_rows() {
+            0 => Err(ApiError::NotFound("User not found".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    //tokens of a deleted user stop working with the next request
+    pub async fn delete_user(&self, tenant: &Tenant, username: &str) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        set_user_warehouses(&mut tx, tenant, username, &[]).await?;
+        tx.exec_drop(
+            "DELETE FROM users WHERE tenant_id = :tenant_id AND username = :username",
+            params! { "tenant_id" => tenant.id(), "username" => username },
+        ).await?;
+
+        if tx.affected_rows() == 0 {
+            return Err(ApiError::NotFound("User not found".to_string()));
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    //key_id is unique across tenants, the key itself decides the tenant. Returns tenant, hash, role and warehouses
+    pub async fn find_api_key(&self, key_id: &str) -> Result<Option<(String, String, Role, WarehouseAccess)>, Error> {
+        let mut conn = self.conn().await?;
+        let key: Option<(String, String, String, bool)> = conn.exec_first(
+            "SELECT tenant_id, key_hash, role, all_warehouses FROM api_keys WHERE key_id = :key_id AND revoked_at IS NULL",
+            params! { "key_id" => key_id },
+        ).await?;
+
+        let Some((tenant_id, key_hash, role, all_warehouses)) = key else {
+            return Ok(None);
+        };
+
+        let warehouses: Vec<String> = conn.exec(
+            "SELECT warehouse FROM api_key_warehouses WHERE tenant_id = :tenant_id AND key_id = :key_id ORDER BY warehouse",
+            params! { "tenant_id" => &tenant_id, "key_id" => key_id },
+        ).await?;
+
+        Ok(Some((tenant_id, key_hash, Role::parse(&role), WarehouseAccess { all_warehouses, warehouses })))
+    }
+
+    pub async fn get_api_keys(&self, tenant: &Tenant) -> Result<Vec<ApiKey>, Error> {
+        let mut conn = self.conn().await?;
+        let mut keys: Vec<ApiKey> = conn.exec(
+            "SELECT key_id, name, role, all_warehouses, created_by, created_at, revoked_at FROM api_keys WHERE tenant_id = :tenant_id ORDER BY created_at",
+            params! { "tenant_id" => tenant.id() },
+        ).await?;
+
+        let assignments: Vec<(String, String)> = conn.exec(
+            "SELECT key_id, warehouse FROM api_key_warehouses WHERE tenant_id = :tenant_id ORDER BY warehouse",
+            params! { "tenant_id" => tenant.id() },
+        ).await?;
+
+        let mut warehouses: HashMap<String, Vec<String>> = HashMap::new();
+        for (key_id, warehouse) in assignments {
+            warehouses.entry(key_id).or_default().push(warehouse);
+        }
+        for key in &mut keys {
+            key.access.warehouses = warehouses.remove(&key.key_id).unwrap_or_default();
+        }
+
+        Ok(keys)
+    }
+
+    pub async fn create_api_key(&self, tenant: &Tenant, key_id: &str, key_hash: &str, request: &CreateApiKeyRequest, created_by: &str) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "INSERT INTO api_keys (tenant_id, key_id, key_hash, name, role, all_warehouses, created_by) VALUES (:tenant_id, :key_id, :key_hash, :name, :role, :all_warehouses, :created_by)",
+            params! { "tenant_id" => tenant.id(), "key_id" => key_id, "key_hash" => key_hash, "name" => &request.name, "role" => request.role.as_str(), "all_warehouses" => request.access.all_warehouses, "created_by" => created_by },
+        ).await?;
+        tx.exec_batch(
+            "INSERT INTO api_key_warehouses (tenant_id, key_id, warehouse) VALUES (:tenant_id, :key_id, :warehouse)",
+            request.access.warehouses.iter().map(|warehouse| params! { "tenant_id" => tenant.id(), "key_id" => key_id, "warehouse" => warehouse }),
+        ).await?;
+
+        tx.commit().await
+    }
+
+    //revoked keys are kept for the record but no longer accepted
+    pub async fn revoke_api_key(&self, tenant: &Tenant, key_id: &str) -> Result<(), ApiError> {
+        let mut conn = self.conn().await?;
+        conn.exec_drop(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE tenant_id = :tenant_id AND key_id = :key_id AND revoked_at IS NULL",
+            params! { "tenant_id" => tenant.id(), "key_id" => key_id },
+        ).await?;
+
+        match conn.affected_rows() {
+            0 => Err(ApiError::NotFound("API key not found".to_string())),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use mysql_async::{prelude::Queryable, Error, params};
+use rust_decimal::Decimal;
+
+use crate::db::database::Database;
+use crate::models::tenant::Tenant;
+use crate::models::valuation::{
+    fifo_layers, round_to, valuation_rows, value_of, CostLayer, CostLayersQuery, CostMethod, CostMovement, GetCostLayers,
+    SetCostMethod, ValuationQuery, ValuationReport,
+};
+
+const MOVEMENT_COLUMNS: &str = "product_code, warehouse, pcs, unit_cost, moved_at";
+
+impl Database {
+    //set the cost method used for valuation of a product, returns false if the product does not exist
+    pub async fn set_cost_method(&self, tenant: &Tenant, request: &SetCostMethod) -> Result<bool, Error> {
+        let query = "UPDATE products SET cost_method = :cost_method WHERE tenant_id = :tenant_id AND product_code = :product_code";
+
+        let named_params = params! {
+            "tenant_id" => tenant.id(),
+            "cost_method" => request.cost_method.as_db_str(),
+            "product_code" => &request.product_code,
+        };
+
+        let mut conn = self.conn().await?;
+        conn.exec_drop(query, named_params).await?;
+
+        Ok(conn.affected_rows() > 0)
+    }
+
+    //get remaining FIFO cost layers of a product per warehouse
+    pub async fn get_cost_layers(&self, tenant: &Tenant, product: &GetCostLayers, filter: &CostLayersQuery) -> Result<Vec<CostLayer>, Error> {
+        let query = format!(
+            "SELECT {} FROM cost_movements WHERE tenant_id = :tenant_id AND product_code = :product_code AND (:warehouse IS NULL OR warehouse = :warehouse)
+             ORDER BY warehouse, moved_at, id",
+            MOVEMENT_COLUMNS,
+        );
+
+        let mut conn = self.conn().await?;
+        let movements: Vec<CostMovement> = conn.exec(query, params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => &product.product_code,
+            "warehouse" => &filter.warehouse,
+        }).await?;
+
+        Ok(movements
+            .chunk_by(|a, b| a.warehouse == b.warehouse)
+            .flat_map(fifo_layers)
+            .collect())
+    }
+
+    //valuation per product and warehouse at the end of the given date
+    pub async fn get_valuation(&self, tenant: &Tenant, request: &ValuationQuery) -> Result<ValuationReport, Error> {
+        let date = request.date.unwrap_or_else(|| Local::now().date_naive());
+
+        let query = format!(
+            "SELECT {} FROM cost_movements
+             WHERE tenant_id = :tenant_id AND moved_at < DATE_ADD(:date, INTERVAL 1 DAY)
+               AND (:warehouse IS NULL OR warehouse = :warehouse)
+               AND (:product_code IS NULL OR product_code = :product_code)
+             ORDER BY product_code, warehouse, moved_at, id",
+            MOVEMENT_COLUMNS,
+        );
+
+        let mut conn = self.conn().await?;
+        let movements: Vec<CostMovement> = conn.exec(query, params! {
+            "tenant_id" => tenant.id(),
+            "date" => date,
+            "warehouse" => &request.warehouse,
+            "product_code" => &request.product_code,
+        }).await?;
+
+        let methods: HashMap<String, String> = conn.exec(
+            "SELECT product_code, cost_method FROM products WHERE tenant_id = :tenant_id AND (:product_code IS NULL OR product_code = :product_code)",
+            params! { "tenant_id" => tenant.id(), "product_code" => &request.product_code },
+        ).await?.into_iter().collect();
+
+        let rows = valuation_rows(&movements, |product_code| {
+            methods.get(product_code).and_then(|method| CostMethod::from_db_str(method)).unwrap_or_default()
+        });
+        let total_value = round_to(rows.iter().map(|row| row.value).sum(), 2);
+
+        Ok(ValuationReport { date, total_value, rows })
+    }
+}
+
+// Records a receipt as a new cost layer. Without a unit cost the last known cost
+// of the product is used, so returns and receipts without a price keep the value.
+pub(crate) async fn record_receipt<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, warehouse: &str, pcs: i32, unit_cost: Option<Decimal>) -> Result<(), Error> {
+    let unit_cost = match unit_cost {
+        Some(unit_cost) => unit_cost,
+        None => last_unit_cost(conn, tenant, product_code).await?,
+    };
+
+    insert_movement(conn, tenant, product_code, warehouse, pcs, Some(unit_cost), "RECEIPT").await
+}
+
+//records an issue, the cost is taken from the layers when the valuation is computed
+pub(crate) async fn record_issue<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, warehouse: &str, pcs: i32) -> Result<(), Error> {
+    insert_movement(conn, tenant, product_code, warehouse, -pcs, None, "ISSUE").await
+}
+
+// Moves pcs between warehouses at the current unit cost of the source warehouse,
+// computed with the cost method of the product.
+pub(crate) async fn record_transfer<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, from_warehouse: &str, to_warehouse: &str, pcs: i32) -> Result<(), Error> {
+    let method: Option<String> = conn.exec_first(
+        "SELECT cost_method FROM products WHERE tenant_id = :tenant_id AND product_code = :product_code",
+        params! { "tenant_id" => tenant.id(), "product_code" => product_code },
+    ).await?;
+    let method = method.as_deref().and_then(CostMethod::from_db_str).unwrap_or_default();
+
+    let movements: Vec<CostMovement> = conn.exec(
+        format!("SELECT {} FROM cost_movements WHERE tenant_id = :tenant_id AND product_code = :product_code AND warehouse = :warehouse ORDER BY moved_at, id", MOVEMENT_COLUMNS),
+        params! { "tenant_id" => tenant.id(), "product_code" => product_code, "warehouse" => from_warehouse },
+    ).await?;
+
+    let unit_cost = match value_of(method, &movements) {
+        (0, _) => last_unit_cost(conn, tenant, product_code).await?,
+        (on_hand, value) => round_to(value / Decimal::from(on_hand), 4),
+    };
+
+    // Transfers are kept apart from receipts and issues so they do not count as outbound
+    insert_movement(conn, tenant, product_code, from_warehouse, -pcs, None, "TRANSFER_OUT").await?;
+    insert_movement(conn, tenant, product_code, to_warehouse, pcs, Some(unit_cost), "TRANSFER_IN").await
+}
+
+async fn insert_movement<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str, warehouse: &str, pcs: i32, unit_cost: Option<Decimal>, movement_type: &str) -> Result<(), Error> {
+    conn.exec_drop(
+        "INSERT INTO cost_movements (tenant_id, product_code, warehouse, pcs, unit_cost, movement_type) VALUES (:tenant_id, :product_code, :warehouse, :pcs, :unit_cost, :movement_type)",
+        params! {
+            "tenant_id" => tenant.id(),
+            "product_code" => product_code,
+            "warehouse" => warehouse,
+            "pcs" => pcs,
+            "unit_cost" => unit_cost,
+            "movement_type" => movement_type,
+        },
+    ).await
+}
+
+async fn last_unit_cost<Q: Queryable>(conn: &mut Q, tenant: &Tenant, product_code: &str) -> Result<Decimal, Error> {
+    let unit_cost: Option<Decimal> = conn.exec_first(
+        "SELECT unit_cost FROM cost_movements WHERE tenant_id = :tenant_id AND product_code = :product_code AND unit_cost IS NOT NULL ORDER BY moved_at DESC, id DESC LIMIT 1",
+        params! { "tenant_id" => tenant.id(), "product_code" => product_code },
+    ).await?;
+
+    Ok(unit_cost.unwrap_or_default())
+}
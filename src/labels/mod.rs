@@ -0,0 +1,86 @@
+pub mod svg;
+pub mod zpl;
+
+use barcoders::sym::code128::Code128;
+use barcoders::sym::ean13::EAN13;
+use barcoders::sym::ean8::EAN8;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::errors::{ApiError, FieldError};
+use crate::models::product_code::is_valid_gtin;
+
+#[derive(Debug, Display)]
+pub enum LabelError {
+    #[display(fmt = "Value can not be encoded in a barcode: {}", _0)]
+    Barcode(String),
+    #[display(fmt = "Value can not be encoded in a QR code: {}", _0)]
+    QrCode(String),
+}
+
+// The value comes from the request, so it is answered like any other unreadable input
+impl From<LabelError> for ApiError {
+    fn from(error: LabelError) -> Self {
+        ApiError::MalformedRequest(vec![FieldError::new("label", "not_encodable", &error.to_string())])
+    }
+}
+
+//output format of a rendered label
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelFormat {
+    #[default]
+    Svg,
+    Zpl,
+}
+
+impl LabelFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LabelFormat::Svg => "image/svg+xml",
+            LabelFormat::Zpl => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+//barcode type printed on location labels
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LocationSymbology {
+    #[default]
+    Code128,
+    Qr,
+}
+
+//data encoded in a location barcode, same separator as in concatenated_string
+pub fn location_barcode_data(warehouse: &str, location: &str) -> String {
+    format!("{}^{}", warehouse, location)
+}
+
+// Valid GTINs get their retail symbology, anything else falls back to Code128.
+pub(crate) fn encode_product_code(product_code: &str) -> Result<Vec<u8>, LabelError> {
+    let barcode_error = |_| LabelError::Barcode(product_code.to_string());
+
+    if is_valid_gtin(product_code) {
+        match product_code.len() {
+            8 => return EAN8::new(product_code).map(|barcode| barcode.encode()).map_err(barcode_error),
+            12 => return EAN13::new(format!("0{}", product_code)).map(|barcode| barcode.encode()).map_err(barcode_error),
+            13 => return EAN13::new(product_code).map(|barcode| barcode.encode()).map_err(barcode_error),
+            _ => {}
+        }
+    }
+
+    encode_code128(product_code)
+}
+
+pub(crate) fn encode_code128(data: &str) -> Result<Vec<u8>, LabelError> {
+    if !data.chars().all(|character| (' '..='~').contains(&character)) {
+        return Err(LabelError::Barcode(data.to_string()));
+    }
+
+    // Character-set B covers printable ASCII
+    Code128::new(format!("\u{0181}{}", data))
+        .map(|barcode| barcode.encode())
+        .map_err(|_| LabelError::Barcode(data.to_string()))
+}
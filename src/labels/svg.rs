@@ -0,0 +1,179 @@
+use qrcode::{Color, QrCode};
+
+use crate::labels::{encode_code128, encode_product_code, location_barcode_data, LabelError, LocationSymbology};
+use crate::models::products::Products;
+
+// Labels are drawn in tenths of a millimetre
+const PRODUCT_LABEL_WIDTH: u32 = 600;
+const PRODUCT_LABEL_HEIGHT: u32 = 300;
+const LOCATION_LABEL_WIDTH: u32 = 1000;
+const LOCATION_LABEL_HEIGHT: u32 = 500;
+
+//product label: name, color and barcode of the product_code
+pub fn product_label(product: &Products) -> Result<String, LabelError> {
+    Ok(document(PRODUCT_LABEL_WIDTH, PRODUCT_LABEL_HEIGHT, &product_label_body(product)?))
+}
+
+//location label: location, warehouse and Code128 or QR code of warehouse+location
+pub fn location_label(warehouse: &str, location: &str, symbology: LocationSymbology) -> Result<String, LabelError> {
+    Ok(document(LOCATION_LABEL_WIDTH, LOCATION_LABEL_HEIGHT, &location_label_body(warehouse, location, symbology)?))
+}
+
+//several location labels stacked on one sheet
+pub fn location_labels(warehouse: &str, locations: &[String], symbology: LocationSymbology) -> Result<String, LabelError> {
+    let mut body = String::new();
+
+    for (index, location) in locations.iter().enumerate() {
+        let offset = index as u32 * LOCATION_LABEL_HEIGHT;
+        body.push_str(&format!(
+            r#"<g transform="translate(0 {})">{}</g>"#,
+            offset,
+            location_label_body(warehouse, location, symbology)?
+        ));
+    }
+
+    Ok(document(LOCATION_LABEL_WIDTH, LOCATION_LABEL_HEIGHT * locations.len() as u32, &body))
+}
+
+fn product_label_body(product: &Products) -> Result<String, LabelError> {
+    let bars = encode_product_code(&product.product_code)?;
+
+    Ok(format!(
+        "{}{}{}{}",
+        text(30, 55, 40, "bold", &product.product_name),
+        text(30, 100, 30, "normal", &product.variant_description()),
+        linear_barcode(&bars, PRODUCT_LABEL_WIDTH, 120, 130),
+        text_centered(PRODUCT_LABEL_WIDTH / 2, 285, 28, &product.product_code),
+    ))
+}
+
+fn location_label_body(warehouse: &str, location: &str, symbology: LocationSymbology) -> Result<String, LabelError> {
+    let data = location_barcode_data(warehouse, location);
+
+    let code = match symbology {
+        LocationSymbology::Code128 => {
+            let bars = encode_code128(&data)?;
+            format!(
+                "{}{}",
+                text(40, 120, 100, "bold", location),
+                linear_barcode(&bars, LOCATION_LABEL_WIDTH, 250, 180)
+            )
+        }
+        LocationSymbology::Qr => format!(
+            "{}{}",
+            text(40, 200, 110, "bold", location),
+            qr_code(&data, 580, 40, 420)?
+        ),
+    };
+
+    Ok(format!(
+        r#"<rect x="2" y="2" width="{}" height="{}" fill="none" stroke="black" stroke-width="4"/>{}{}"#,
+        LOCATION_LABEL_WIDTH - 4,
+        LOCATION_LABEL_HEIGHT - 4,
+        code,
+        text(40, 470, 45, "normal", warehouse),
+    ))
+}
+
+fn document(width: u32, height: u32, body: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{}mm" height="{}mm" viewBox="0 0 {} {}"><rect width="100%" height="100%" fill="white"/>{}</svg>"#,
+        width / 10,
+        height / 10,
+        width,
+        height,
+        body
+    )
+}
+
+// Draws the encoded modules centered on the label, merging neighbouring bars into one rect.
+// Data too long for the label is drawn from the left edge with the narrowest modules, it runs over the edge instead of panicking.
+fn linear_barcode(bars: &[u8], label_width: u32, y: u32, height: u32) -> String {
+    let module = (label_width.saturating_sub(60) / bars.len().max(1) as u32).max(1);
+    let x = label_width.saturating_sub(module * bars.len() as u32) / 2;
+
+    let mut rects = String::new();
+    let mut index = 0;
+
+    while index < bars.len() {
+        if bars[index] == 1 {
+            let start = index;
+            while index < bars.len() && bars[index] == 1 {
+                index += 1;
+            }
+            rects.push_str(&format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black"/>"#,
+                x + start as u32 * module,
+                y,
+                (index - start) as u32 * module,
+                height
+            ));
+        } else {
+            index += 1;
+        }
+    }
+
+    rects
+}
+
+fn qr_code(data: &str, x: u32, y: u32, size: u32) -> Result<String, LabelError> {
+    let code = QrCode::new(data.as_bytes()).map_err(|_| LabelError::QrCode(data.to_string()))?;
+    let width = code.width();
+    let module = (size / width as u32).max(1);
+
+    let rects: String = code
+        .to_colors()
+        .iter()
+        .enumerate()
+        .filter(|(_, color)| **color == Color::Dark)
+        .map(|(index, _)| {
+            format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="black"/>"#,
+                x + (index % width) as u32 * module,
+                y + (index / width) as u32 * module,
+                module,
+                module
+            )
+        })
+        .collect();
+
+    Ok(rects)
+}
+
+fn text(x: u32, y: u32, size: u32, weight: &str, value: &str) -> String {
+    format!(
+        r#"<text x="{}" y="{}" font-family="Arial, sans-serif" font-size="{}" font-weight="{}">{}</text>"#,
+        x, y, size, weight, escape(value)
+    )
+}
+
+fn text_centered(x: u32, y: u32, size: u32, value: &str) -> String {
+    format!(
+        r#"<text x="{}" y="{}" font-family="Arial, sans-serif" font-size="{}" text-anchor="middle">{}</text>"#,
+        x, y, size, escape(value)
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barcodes_wider_than_the_label_do_not_panic() {
+        let location = "M".repeat(90);
+        let label = location_label("HALA 5", &location, LocationSymbology::Code128).unwrap();
+        assert!(label.contains(r#"<rect x="0" y="250" width="2""#));
+
+        let narrow = linear_barcode(&[1, 0, 1], 10, 0, 10);
+        assert_eq!(narrow, r#"<rect x="3" y="0" width="1" height="10" fill="black"/><rect x="5" y="0" width="1" height="10" fill="black"/>"#);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::labels::{location_barcode_data, LocationSymbology};
+use crate::models::product_code::is_valid_gtin;
+use crate::models::products::Products;
+
+//product label for thermal printers, the printer draws the barcode itself
+pub fn product_label(product: &Products) -> String {
+    let code = &product.product_code;
+
+    // Retail symbologies get the code without check digit, the printer adds it
+    let barcode = if is_valid_gtin(code) {
+        match code.len() {
+            8 => format!("^B8N,100,Y,N^FD{}^FS", &code[..7]),
+            12 => format!("^BUN,100,Y,N,Y^FD{}^FS", &code[..11]),
+            13 => format!("^BEN,100,Y,N^FD{}^FS", &code[..12]),
+            _ => format!("^BCN,100,Y,N,N^FH\\^FD{}^FS", field(code)),
+        }
+    } else {
+        format!("^BCN,100,Y,N,N^FH\\^FD{}^FS", field(code))
+    };
+
+    format!(
+        "^XA^CI28^PW480^LL240\n^FO20,20^A0N,34,34^FH\\^FD{}^FS\n^FO20,60^A0N,26,26^FH\\^FD{}^FS\n^FO40,100^BY2{}\n^XZ\n",
+        field(&product.product_name),
+        field(&product.variant_description()),
+        barcode
+    )
+}
+
+//location label for thermal printers
+pub fn location_label(warehouse: &str, location: &str, symbology: LocationSymbology) -> String {
+    let data = field(&location_barcode_data(warehouse, location));
+
+    let code = match symbology {
+        LocationSymbology::Code128 => format!(
+            "^FO30,20^A0N,90,90^FH\\^FD{}^FS\n^FO30,130^BY3^BCN,150,N,N,N^FH\\^FD{}^FS",
+            field(location),
+            data
+        ),
+        LocationSymbology::Qr => format!(
+            "^FO30,60^A0N,100,100^FH\\^FD{}^FS\n^FO500,10^BQN,2,8^FH\\^FDQA,{}^FS",
+            field(location),
+            data
+        ),
+    };
+
+    format!(
+        "^XA^CI28^PW800^LL400\n{}\n^FO30,340^A0N,40,40^FH\\^FD{}^FS\n^XZ\n",
+        code,
+        field(warehouse)
+    )
+}
+
+//several location labels in one print job
+pub fn location_labels(warehouse: &str, locations: &[String], symbology: LocationSymbology) -> String {
+    locations
+        .iter()
+        .map(|location| location_label(warehouse, location, symbology))
+        .collect()
+}
+
+// Field data is sent with ^FH, so the ZPL control characters are hex escaped.
+fn field(value: &str) -> String {
+    value
+        .replace('\\', "\\5C")
+        .replace('^', "\\5E")
+        .replace('~', "\\7E")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn location_label_escapes_the_separator() {
+        let label = location_label("HALA 5", "M5-A-1", LocationSymbology::Qr);
+
+        assert!(label.starts_with("^XA"));
+        assert!(label.contains("^FDQA,HALA 5\\5EM5-A-1^FS"));
+        assert!(label.trim_end().ends_with("^XZ"));
+    }
+
+    #[test]
+    fn product_label_uses_upc_for_valid_twelve_digit_codes() {
+        let product = Products {
+            product_code: "806807071423".to_string(),
+            product_name: "Bamboo cutting board set".to_string(),
+            family_code: None,
+            attributes: BTreeMap::from([("color".to_string(), "Peach".to_string())]),
+        };
+
+        assert!(product_label(&product).contains("^BUN,100,Y,N,Y^FD80680707142^FS"));
+    }
+}
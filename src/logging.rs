@@ -0,0 +1,160 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage};
+use rand::RngCore;
+use tracing::{Instrument, Level};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{LogFormat, LoggingConfig};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Every span is logged when it closes, with its duration: HTTP requests and the
+// mysql_async::prepare / mysql_async::exec spans of every query
+pub fn init(config: &LoggingConfig) {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(&config.level))
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(true);
+
+    match config.format {
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+        LogFormat::Text => builder.init(),
+    }
+}
+
+//id of the HTTP request, taken from X-Request-Id or generated, available as request extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    // Ids sent by a proxy or client are kept when they are 1 to 128 visible ASCII characters
+    pub fn from_header(value: Option<&HeaderValue>) -> RequestId {
+        let sent = value
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| (1..=128).contains(&id.len()) && id.chars().all(|c| c.is_ascii_graphic()));
+
+        match sent {
+            Some(id) => RequestId(id.to_string()),
+            None => RequestId::generate(),
+        }
+    }
+
+    fn generate() -> RequestId {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        RequestId(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+// Runs every request in a span carrying the request id, so all log lines of the request,
+// including the database queries, can be found by it. The id is returned in X-Request-Id
+pub struct RequestTracing;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestTracingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let request_id = RequestId::from_header(req.headers().get(REQUEST_ID_HEADER));
+        // tenant and user are filled in by the authentication middleware
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id.0,
+            method = %req.method(),
+            path = %req.path(),
+            tenant = tracing::field::Empty,
+            user = tracing::field::Empty,
+        );
+        req.extensions_mut().insert(request_id.clone());
+
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let result = service.call(req).await;
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+
+                let mut response = match result {
+                    Ok(response) => response,
+                    Err(error) => {
+                        tracing::error!(elapsed_ms, error = %error, "request failed");
+                        return Err(error);
+                    }
+                };
+
+                let status = response.status().as_u16();
+                match status {
+                    500.. => tracing::event!(Level::ERROR, status, elapsed_ms, "request finished"),
+                    400..=499 => tracing::event!(Level::WARN, status, elapsed_ms, "request finished"),
+                    _ => tracing::event!(Level::INFO, status, elapsed_ms, "request finished"),
+                }
+
+                if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                    response.headers_mut().insert(HeaderName::from_static("x-request-id"), value);
+                }
+
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn request_ids_are_kept_or_generated() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestTracing)
+                .route("/healthz", web::get().to(HttpResponse::Ok)),
+        ).await;
+
+        let sent = test::TestRequest::get().uri("/healthz").insert_header((REQUEST_ID_HEADER, "scanner-7-000123")).to_request();
+        let response = test::call_service(&app, sent).await;
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "scanner-7-000123");
+
+        let response = test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap().len(), 32);
+
+        assert_eq!(RequestId::from_header(Some(&HeaderValue::from_static("two words"))).0.len(), 32);
+    }
+}
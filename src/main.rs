@@ -1,37 +1,232 @@
-use actix_web::{HttpServer, App, web::Data};
+use std::time::Duration;
 
+use actix_web::{HttpServer, App, dev::{Service, ServiceResponse}, web::Data};
+use dotenv::dotenv;
+use utoipa::OpenApi;
+use utoipa_actix_web::{service_config::ServiceConfig, AppExt};
+use utoipa_swagger_ui::SwaggerUi;
+
+mod auth;
 mod db;
 mod models;
 mod api;
+mod labels;
+mod config;
+mod shutdown;
+mod logging;
+mod metrics;
 
+use crate::auth::credentials::JwtKeys;
+use crate::auth::middleware::Authentication;
+use crate::config::{Args, Config, ModuleToggles};
+use crate::logging::RequestTracing;
+use crate::metrics::{Metrics, RequestMetrics};
 use crate::db::database::Database;
+use crate::shutdown::InFlightMutations;
+use crate::models::errors::{json_config, path_config, query_config};
 // ---------  TEST 1 , TEST 2 --------------- //
 use api::mysqlapi::{get_unique_identifiers, add_or_update_unique_identifier,  remove_unique_identifier};
+use api::lots::{get_expiring_stock, allocate_sales_order};
+use api::serials::{get_serial_number, set_serial_tracking};
+use api::products::{create_product, set_product_attributes, create_product_family, get_product_family};
+use api::labels::{get_product_label, get_location_label, get_location_range_labels};
+use api::scanning::parse_scan;
+use api::packaging::{get_packaging, set_packaging};
+use api::stock_status::{change_stock_status, get_available_stock};
+use api::abc::{recompute_abc_classification, get_abc_classification};
+use api::reports::get_slow_moving_stock;
+use api::valuation::{set_cost_method, get_cost_layers, get_valuation};
+use api::returns::{create_return_authorization, get_return_authorization, receive_return, dispose_return};
+use api::health::{healthz, readyz};
+use api::metrics::get_metrics;
+use api::openapi::ApiDoc;
+use api::users::{login, get_users, create_user, change_user_password, set_user_access, delete_user, get_api_keys, create_api_key, revoke_api_key};
+use api::handling_units::{get_handling_unit, create_handling_unit, pack_handling_unit, unpack_handling_unit, nest_handling_unit, move_handling_unit};
+
 
 
+// Every route of the API, the OpenAPI document is collected from these same
+// registrations so a routed handler can not be left out of it
+fn configure_routes(cfg: &mut ServiceConfig, modules: &ModuleToggles) {
+    cfg.service(healthz)
+        .service(readyz)
+        .service(get_metrics)
+        .service(login)
+        .service(get_users)
+        .service(create_user)
+        .service(change_user_password)
+        .service(set_user_access)
+        .service(delete_user)
+        .service(get_api_keys)
+        .service(create_api_key)
+        .service(revoke_api_key)
+        .service(get_unique_identifiers)
+        .service(add_or_update_unique_identifier)
+        .service(remove_unique_identifier)
+        .service(get_expiring_stock)
+        .service(allocate_sales_order)
+        .service(create_product)
+        .service(set_product_attributes)
+        .service(create_product_family)
+        .service(get_product_family)
+        .service(change_stock_status)
+        .service(get_available_stock);
+    configure_modules(cfg, modules);
+}
+
+// Optional modules are only routed when enabled in the [modules] section
+fn configure_modules(cfg: &mut ServiceConfig, modules: &ModuleToggles) {
+    if modules.serials {
+        cfg.service(get_serial_number)
+            .service(set_serial_tracking);
+    }
+    if modules.labels {
+        cfg.service(get_product_label)
+            .service(get_location_label)
+            .service(get_location_range_labels);
+    }
+    if modules.scanning {
+        cfg.service(parse_scan);
+    }
+    if modules.packaging {
+        cfg.service(get_packaging)
+            .service(set_packaging);
+    }
+    if modules.handling_units {
+        cfg.service(create_handling_unit)
+            .service(pack_handling_unit)
+            .service(unpack_handling_unit)
+            .service(nest_handling_unit)
+            .service(move_handling_unit)
+            .service(get_handling_unit);
+    }
+    if modules.returns {
+        cfg.service(create_return_authorization)
+            .service(receive_return)
+            .service(dispose_return)
+            .service(get_return_authorization);
+    }
+    if modules.valuation {
+        cfg.service(set_cost_method)
+            .service(get_cost_layers)
+            .service(get_valuation);
+    }
+    if modules.reports {
+        cfg.service(get_slow_moving_stock);
+    }
+    if modules.abc {
+        cfg.service(recompute_abc_classification)
+            .service(get_abc_classification);
+    }
+}
 
 #[actix_web::main]
 async fn main()-> std::io::Result<()> {
-    match Database::init().await {
+    dotenv().ok();
+
+    let (config, args) = match Args::parse(std::env::args()).and_then(|args| Ok((Config::load(&args)?, args))) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Error loading the configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if args.print_config {
+        print!("{}", config.to_printable_toml());
+        std::process::exit(0);
+    }
+
+    logging::init(&config.logging);
+
+    match Database::init(&config.database).await {
         Ok(db) => {
-            println!("Database initialized successfully");
+            tracing::info!("Database initialized successfully");
+            // Stock changes are counted by the database layer in the metrics served on /metrics
+            let metrics = Data::new(Metrics::new());
+            let db = db.with_empty_rows(config.stock.empty_rows).with_metrics(metrics.clone().into_inner());
+
+            if let Some((tenant, username)) = &args.create_user {
+                match auth::create_user_from_env(&db, tenant, username).await {
+                    Ok(()) => tracing::info!(username = %username, tenant = %tenant, "User created"),
+                    Err(err) => {
+                        tracing::error!(error = %err, "Error creating the user");
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+
             let db_data = Data::new(db);
+            let modules = config.modules.clone();
+            let in_flight = InFlightMutations::default();
+            let tracker = in_flight.clone();
+            let server_db = db_data.clone();
+            let jwt_keys = Data::new(JwtKeys::new(&config.auth));
+            let products = Data::new(config.products.clone());
 
             // --- TEST 1, TEST 2  --- //
-            HttpServer::new(move||{
-                App::new()
-                    .app_data(db_data.clone())
-                    .service(get_unique_identifiers)
-                    .service(add_or_update_unique_identifier)
-                    .service(remove_unique_identifier)
-                    
+            let server = HttpServer::new(move||{
+                let tracker = tracker.clone();
+                let (app, openapi) = App::new()
+                    .into_utoipa_app()
+                    .openapi(ApiDoc::openapi())
+                    .configure(|cfg| configure_routes(cfg, &modules))
+                    .split_for_parts();
+
+                app.app_data(server_db.clone())
+                    .app_data(jwt_keys.clone())
+                    .app_data(metrics.clone())
+                    .app_data(products.clone())
+                    // Every route except the probes and the login needs a JWT or an API key
+                    .wrap(Authentication)
+                    // Counts stock mutations so the shutdown can tell whether all of them finished,
+                    // and refuses new ones with 503 once it started
+                    .wrap_fn(move |req, srv| {
+                        let call = match tracker.track(req.method()) {
+                            Ok(mutation) => Ok((srv.call(req), mutation)),
+                            Err(error) => Err(req.error_response(error)),
+                        };
+                        async move {
+                            match call {
+                                Ok((response, mutation)) => {
+                                    let response = response.await;
+                                    drop(mutation);
+                                    response.map(ServiceResponse::map_into_boxed_body)
+                                }
+                                Err(refused) => Ok(refused),
+                            }
+                        }
+                    })
+                    // Malformed bodies, paths and query strings get the same JSON errors as failed validation
+                    .app_data(json_config())
+                    .app_data(path_config())
+                    .app_data(query_config())
+                    .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", openapi))
+                    // Counts rejected requests too, under the route they were sent to
+                    .wrap(RequestMetrics)
+                    // Outermost, so the request id and timing also cover rejected requests
+                    .wrap(RequestTracing)
             })
-            .bind("127.0.0.1:8080")?
-            .run()
-            .await
+            .workers(config.server.workers)
+            .keep_alive(Duration::from_secs(config.server.keep_alive_secs))
+            .client_request_timeout(Duration::from_secs(config.server.client_request_timeout_secs))
+            .shutdown_timeout(config.server.shutdown_timeout_secs)
+            // SIGINT and SIGTERM are handled by shutdown::on_signal
+            .disable_signals()
+            .bind((config.server.bind_address.as_str(), config.server.port))?
+            .run();
+
+            let shutdown_timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+            let signals = actix_web::rt::spawn(shutdown::on_signal(server.handle(), in_flight, shutdown_timeout));
+            server.await?;
+            signals.abort();
+
+            shutdown::close(&db_data, shutdown_timeout).await;
+            Ok(())
         }
         Err(err) => {
-            eprintln!("Error connecting to the database: {}", err);
+            tracing::error!(error = %err, "Error connecting to the database");
             std::process::exit(1);
         }
     }
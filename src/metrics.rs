@@ -0,0 +1,237 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::Data;
+use actix_web::Error;
+use prometheus::proto::MetricFamily;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+use crate::db::pool::PoolStats;
+use crate::models::tenant::Tenant;
+
+// Requests that match no route are counted under one label, so random paths do not create new series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+// Latency buckets in seconds, from a cached read to a slow report
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// Prometheus metrics of the server, registered as app data. Business metrics carry a tenant label
+// and /metrics only returns the series of the tenant that asks
+pub struct Metrics{
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pcs_added: IntCounterVec,
+    pcs_removed: IntCounterVec,
+    insufficient_stock: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let registry = Registry::new_custom(Some("wms".to_string()), None).expect("metric prefix is valid");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route, method and status"),
+            &["method", "route", "status"],
+        ).expect("metric is valid");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and method").buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "route"],
+        ).expect("metric is valid");
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections: in_use, waiting, min and max"),
+            &["state"],
+        ).expect("metric is valid");
+        let pcs_added = IntCounterVec::new(
+            Opts::new("stock_pcs_added_total", "Pcs added to stock per warehouse"),
+            &["tenant", "warehouse"],
+        ).expect("metric is valid");
+        let pcs_removed = IntCounterVec::new(
+            Opts::new("stock_pcs_removed_total", "Pcs removed from stock per warehouse"),
+            &["tenant", "warehouse"],
+        ).expect("metric is valid");
+        let insufficient_stock = IntCounterVec::new(
+            Opts::new("stock_removals_insufficient_total", "Removals rejected because there were not enough pcs"),
+            &["tenant", "warehouse"],
+        ).expect("metric is valid");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pcs_added.clone()),
+            Box::new(pcs_removed.clone()),
+            Box::new(insufficient_stock.clone()),
+        ] {
+            registry.register(collector).expect("metric names are unique");
+        }
+
+        Metrics { registry, http_requests, http_duration, pool_connections, pcs_added, pcs_removed, insufficient_stock }
+    }
+
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed_secs: f64) {
+        self.http_requests.with_label_values(&[method, route, &status.to_string()]).inc();
+        self.http_duration.with_label_values(&[method, route]).observe(elapsed_secs);
+    }
+
+    pub fn record_added(&self, tenant: &Tenant, warehouse: &str, pcs: i32) {
+        self.pcs_added.with_label_values(&[tenant.id(), warehouse]).inc_by(pcs.max(0) as u64);
+    }
+
+    pub fn record_removed(&self, tenant: &Tenant, warehouse: &str, pcs: i32) {
+        self.pcs_removed.with_label_values(&[tenant.id(), warehouse]).inc_by(pcs.max(0) as u64);
+    }
+
+    pub fn record_insufficient_stock(&self, tenant: &Tenant, warehouse: &str) {
+        self.insufficient_stock.with_label_values(&[tenant.id(), warehouse]).inc();
+    }
+
+    // Prometheus text format for one tenant. The pool is read when scraped and the total stock
+    // per warehouse comes from the database, so it is right after restarts and direct changes
+    pub fn render(&self, tenant: &Tenant, pool: &PoolStats, stock: &[(String, i64)]) -> String {
+        for (state, value) in [("in_use", pool.in_use), ("waiting", pool.waiting), ("min", pool.min), ("max", pool.max)] {
+            self.pool_connections.with_label_values(&[state]).set(value as i64);
+        }
+
+        let mut families = self.registry.gather();
+        families.push(stock_family(tenant, stock));
+        families.iter_mut().for_each(|family| keep_tenant(family, tenant));
+        families.retain(|family| !family.get_metric().is_empty());
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&families, &mut buffer).expect("metrics are written to memory");
+        String::from_utf8(buffer).expect("metrics are UTF-8")
+    }
+}
+
+// Gauge of this scrape only, a shared one would mix the warehouses of tenants scraped at the same time
+fn stock_family(tenant: &Tenant, stock: &[(String, i64)]) -> MetricFamily {
+    let gauge = IntGaugeVec::new(
+        Opts::new("stock_pcs", "Pcs currently in stock per warehouse").namespace("wms"),
+        &["tenant", "warehouse"],
+    ).expect("metric is valid");
+
+    for (warehouse, pcs) in stock {
+        gauge.with_label_values(&[tenant.id(), warehouse]).set(*pcs);
+    }
+
+    let registry = Registry::new();
+    registry.register(Box::new(gauge)).expect("metric is registered once");
+    registry.gather().pop().unwrap_or_default()
+}
+
+//drops series labelled with another tenant
+fn keep_tenant(family: &mut MetricFamily, tenant: &Tenant) {
+    let metrics = family.take_metric().into_iter().filter(|metric| {
+        metric.get_label().iter().all(|label| label.get_name() != "tenant" || label.get_value() == tenant.id())
+    });
+    family.set_metric(metrics.collect());
+}
+
+// Counts every request and its latency under the matched route pattern, e.g. /users/{username}
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let metrics = req.app_data::<Data<Metrics>>().expect("Metrics are registered as app data").clone();
+        let method = req.method().to_string();
+        let route = req.match_pattern().unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = service.call(req).await;
+
+            let status = match &result {
+                Ok(response) => response.status().as_u16(),
+                Err(error) => error.as_response_error().status_code().as_u16(),
+            };
+            metrics.record_request(&method, &route, status, started.elapsed().as_secs_f64());
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn requests_are_counted_per_route() {
+        use actix_web::test;
+
+        let metrics = Data::new(Metrics::new());
+        let app = test::init_service(
+            App::new()
+                .app_data(metrics.clone())
+                .wrap(RequestMetrics)
+                .route("/users/{username}", web::get().to(HttpResponse::Ok)),
+        ).await;
+
+        test::call_service(&app, test::TestRequest::get().uri("/users/marko").to_request()).await;
+        test::call_service(&app, test::TestRequest::get().uri("/users/ana").to_request()).await;
+        test::call_service(&app, test::TestRequest::get().uri("/wp-admin").to_request()).await;
+
+        let text = metrics.render(&Tenant::new("default").unwrap(), &PoolStats { min: 10, max: 100, in_use: 2, waiting: 0 }, &[]);
+        assert!(text.contains(r#"wms_http_requests_total{method="GET",route="/users/{username}",status="200"} 2"#));
+        assert!(text.contains(r#"wms_http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+        assert!(text.contains(r#"wms_http_request_duration_seconds_count{method="GET",route="/users/{username}"} 2"#));
+        assert!(text.contains(r#"wms_db_pool_connections{state="in_use"} 2"#));
+    }
+
+    #[test]
+    fn tenants_only_see_their_own_stock() {
+        let metrics = Metrics::new();
+        let acme = Tenant::new("acme").unwrap();
+        let other = Tenant::new("other").unwrap();
+        let pool = PoolStats { min: 10, max: 100, in_use: 0, waiting: 0 };
+
+        metrics.record_added(&acme, "HALA 5", 12);
+        metrics.record_removed(&acme, "HALA 5", 5);
+        metrics.record_insufficient_stock(&acme, "HALA 5");
+        metrics.record_added(&other, "HALA 1", 3);
+
+        let text = metrics.render(&acme, &pool, &[("HALA 5".to_string(), 7)]);
+        assert!(text.contains(r#"wms_stock_pcs_added_total{tenant="acme",warehouse="HALA 5"} 12"#));
+        assert!(text.contains(r#"wms_stock_pcs_removed_total{tenant="acme",warehouse="HALA 5"} 5"#));
+        assert!(text.contains(r#"wms_stock_removals_insufficient_total{tenant="acme",warehouse="HALA 5"} 1"#));
+        assert!(text.contains(r#"wms_stock_pcs{tenant="acme",warehouse="HALA 5"} 7"#));
+        assert!(!text.contains("other"));
+    }
+}
@@ -0,0 +1,245 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+
+use crate::models::incoming::take_column;
+use crate::models::products::parse_attributes;
+use crate::models::valuation::round_to;
+
+//what the products are ranked by
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AbcBasis {
+    #[default]
+    Volume,
+    Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+pub enum AbcClass {
+    A,
+    B,
+    C,
+}
+
+impl AbcClass {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AbcClass::A => "A",
+            AbcClass::B => "B",
+            AbcClass::C => "C",
+        }
+    }
+}
+
+fn default_period_days() -> i32 { 90 }
+fn default_a_share() -> f64 { 80.0 }
+fn default_b_share() -> f64 { 15.0 }
+fn default_c_share() -> f64 { 5.0 }
+
+//request for recomputing the ABC classification, shares are percentages of the total outbound
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+#[validate(schema(function = "validate_abc_shares"))]
+pub struct AbcAnalysisRequest{
+    #[serde(default = "default_period_days")]
+    #[validate(range(min = 1, max = 3650, message = "Period must be between 1 and 3650 days"))]
+    pub period_days: i32,
+    #[serde(default)]
+    pub basis: AbcBasis,
+    #[serde(default = "default_a_share")]
+    #[validate(range(min = 0.0, max = 100.0, message = "Share must be between 0 and 100"))]
+    pub a_share: f64,
+    #[serde(default = "default_b_share")]
+    #[validate(range(min = 0.0, max = 100.0, message = "Share must be between 0 and 100"))]
+    pub b_share: f64,
+    #[serde(default = "default_c_share")]
+    #[validate(range(min = 0.0, max = 100.0, message = "Share must be between 0 and 100"))]
+    pub c_share: f64,
+}
+
+fn validate_abc_shares(request: &AbcAnalysisRequest) -> Result<(), ValidationError> {
+    if (request.a_share + request.b_share + request.c_share - 100.0).abs() > 0.001 {
+        let mut error = ValidationError::new("abc_shares");
+        error.message = Some(Cow::from("A, B and C shares must add up to 100"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+//outbound volume and value of a product over the analysed period
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductOutbound{
+    pub product_code: String,
+    pub pcs: i64,
+    pub value: Decimal,
+}
+
+impl FromRow for ProductOutbound {
+    fn from_row(row: Row) -> Self{
+
+        let (product_code, pcs, value):(String, i64, Decimal) = mysql_async::from_row(row);
+
+        ProductOutbound{product_code, pcs, value}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (product_code, pcs, value):(String, i64, Decimal) = mysql_async::from_row(row);
+
+        Ok(ProductOutbound{product_code, pcs, value})
+    }
+}
+
+//ABC class of a product as stored on products
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct ProductAbcClass{
+    pub product_code: String,
+    pub product_name: String,
+    pub attributes: BTreeMap<String, String>,
+    pub abc_class: Option<String>,
+    pub abc_measure: Option<Decimal>,
+    pub abc_computed_at: Option<NaiveDateTime>,
+}
+
+impl FromRow for ProductAbcClass {
+    fn from_row(row: Row) -> Self{
+
+        ProductAbcClass::from_row_opt(row).expect("Could not convert row to ProductAbcClass")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let mut columns = row.clone();
+
+        let class = (|| Some(ProductAbcClass{
+            product_code: take_column(&mut columns, "product_code")?,
+            product_name: take_column(&mut columns, "product_name")?,
+            attributes: parse_attributes(take_column(&mut columns, "attributes")?)?,
+            abc_class: take_column(&mut columns, "abc_class")?,
+            abc_measure: take_column(&mut columns, "abc_measure")?,
+            abc_computed_at: take_column(&mut columns, "abc_computed_at")?,
+        }))();
+
+        class.ok_or(mysql_async::FromRowError(row))
+    }
+}
+
+//classification result of a single product
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct AbcClassification{
+    pub product_code: String,
+    pub abc_class: AbcClass,
+    pub measure: Decimal,
+    pub cumulative_share: Decimal,
+}
+
+//share of the request as a Decimal, the validated f64 is kept to 4 decimals
+fn share(percent: f64) -> Decimal {
+    round_to(Decimal::try_from(percent).unwrap_or_default(), 4)
+}
+
+// Ranks products by the measure, highest first. A product belongs to A while the
+// share of the products ranked before it is below the A share, then to B while
+// below A + B, everything else is C. Products without outbound are always C.
+// Values are summed as Decimal so the shares of large totals do not drift.
+pub fn classify(outbound: &[ProductOutbound], request: &AbcAnalysisRequest) -> Vec<AbcClassification> {
+    let measure_of = |item: &ProductOutbound| match request.basis {
+        AbcBasis::Volume => Decimal::from(item.pcs),
+        AbcBasis::Value => item.value,
+    };
+    let a_share = share(request.a_share);
+    let b_share = share(request.b_share);
+    let hundred = Decimal::ONE_HUNDRED;
+
+    let mut ranked: Vec<(&ProductOutbound, Decimal)> = outbound.iter().map(|item| (item, measure_of(item))).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.product_code.cmp(&b.0.product_code)));
+
+    let total: Decimal = ranked.iter().map(|(_, measure)| measure).sum();
+    let mut cumulative = Decimal::ZERO;
+
+    ranked.into_iter().map(|(item, measure)| {
+        let share_before = if total > Decimal::ZERO { cumulative / total * hundred } else { hundred };
+        cumulative += measure;
+
+        let abc_class = if measure <= Decimal::ZERO {
+            AbcClass::C
+        } else if share_before < a_share {
+            AbcClass::A
+        } else if share_before < a_share + b_share {
+            AbcClass::B
+        } else {
+            AbcClass::C
+        };
+
+        AbcClassification {
+            product_code: item.product_code.clone(),
+            abc_class,
+            measure,
+            cumulative_share: if total > Decimal::ZERO { round_to(cumulative / total * hundred, 4) } else { Decimal::ZERO },
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outbound(product_code: &str, pcs: i64, value: i64) -> ProductOutbound {
+        ProductOutbound { product_code: product_code.to_string(), pcs, value: Decimal::from(value) }
+    }
+
+    #[test]
+    fn classifies_by_cumulative_share() {
+        let request: AbcAnalysisRequest = serde_json::from_str("{}").unwrap();
+        let items = vec![
+            outbound("1", 10, 500),
+            outbound("2", 700, 10),
+            outbound("3", 150, 20),
+            outbound("4", 100, 30),
+            outbound("5", 40, 40),
+            outbound("6", 0, 0),
+        ];
+
+        let classes: Vec<(String, AbcClass)> = classify(&items, &request).into_iter().map(|c| (c.product_code, c.abc_class)).collect();
+        assert_eq!(classes, vec![
+            ("2".to_string(), AbcClass::A),
+            ("3".to_string(), AbcClass::A),
+            ("4".to_string(), AbcClass::B),
+            ("5".to_string(), AbcClass::C),
+            ("1".to_string(), AbcClass::C),
+            ("6".to_string(), AbcClass::C),
+        ]);
+
+        let by_value = AbcAnalysisRequest { basis: AbcBasis::Value, ..request };
+        assert_eq!(classify(&items, &by_value)[0].product_code, "1");
+    }
+
+    #[test]
+    fn cent_values_are_summed_exactly() {
+        // The third product starts exactly at the A share, so it is B, and the shares add up to exactly 100
+        let request: AbcAnalysisRequest = serde_json::from_str(r#"{"basis": "value", "a_share": 60, "b_share": 30, "c_share": 10}"#).unwrap();
+        let items = vec![
+            ProductOutbound { product_code: "1".to_string(), pcs: 1, value: Decimal::new(2, 1) },
+            ProductOutbound { product_code: "2".to_string(), pcs: 1, value: Decimal::new(1, 1) },
+            ProductOutbound { product_code: "3".to_string(), pcs: 1, value: Decimal::new(1, 1) },
+            ProductOutbound { product_code: "4".to_string(), pcs: 1, value: Decimal::new(1, 1) },
+        ];
+
+        let classes = classify(&items, &request);
+        assert_eq!(classes[2].abc_class, AbcClass::B);
+        assert_eq!(classes[3].cumulative_share, Decimal::ONE_HUNDRED);
+        assert_eq!(classes[1].cumulative_share, Decimal::new(600000, 4));
+    }
+
+    #[test]
+    fn shares_must_add_up_to_100() {
+        let request: AbcAnalysisRequest = serde_json::from_str(r#"{"a_share": 70, "b_share": 20, "c_share": 5}"#).unwrap();
+        assert!(request.validate().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+//what happens with a stock row once its last pcs are taken out, empty_rows in the [stock] section
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyRowMode {
+    //row is deleted, the location history is lost
+    #[default]
+    Delete,
+    //row stays with 0 pcs and the date it was emptied
+    Keep,
+    //row is copied to stock_location_history with the date it was emptied, then deleted
+    Archive,
+}
+
+impl EmptyRowMode {
+    pub fn parse(value: &str) -> Option<EmptyRowMode> {
+        match value.trim().to_lowercase().as_str() {
+            "delete" => Some(EmptyRowMode::Delete),
+            "keep" => Some(EmptyRowMode::Keep),
+            "archive" => Some(EmptyRowMode::Archive),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for EmptyRowMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        EmptyRowMode::parse(value).ok_or(())
+    }
+}
+
+//query string for GET /unique_identifiers
+#[derive(Serialize, Deserialize, Validate, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StockListQuery{
+    #[serde(default)]
+    pub breakdown: bool,
+    //rows with 0 pcs that are kept at their location
+    #[serde(default)]
+    pub include_empty: bool,
+    //only rows emptied within the last N days, kept and archived
+    #[validate(range(min = 1, max = 3650, message = "Days must be between 1 and 3650"))]
+    pub emptied_within_days: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_row_mode_is_parsed_case_insensitive() {
+        assert_eq!(EmptyRowMode::parse("Keep"), Some(EmptyRowMode::Keep));
+        assert_eq!(EmptyRowMode::parse(" archive "), Some(EmptyRowMode::Archive));
+        assert_eq!(EmptyRowMode::parse("delete"), Some(EmptyRowMode::Delete));
+        assert_eq!(EmptyRowMode::parse("drop"), None);
+    }
+}
@@ -0,0 +1,231 @@
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::web::{JsonConfig, PathConfig, QueryConfig};
+use actix_web::{HttpResponse, ResponseError};
+use derive_more::Display;
+use serde::Serialize;
+use utoipa::ToSchema;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+//errors returned by the API, every variant has its own status code and machine-readable code
+#[derive(Debug, Display)]
+pub enum ApiError {
+    #[display(fmt = "{}", _0)]
+    InsufficientStock(String),
+    #[display(fmt = "{}", _0)]
+    NotFound(String),
+    #[display(fmt = "{}", _0)]
+    Conflict(String),
+    #[display(fmt = "Invalid input. Please correct the listed fields.")]
+    Validation(Vec<FieldError>),
+    #[display(fmt = "Request could not be read. Please correct the listed fields.")]
+    MalformedRequest(Vec<FieldError>),
+    #[display(fmt = "{}", _0)]
+    Unauthorized(String),
+    #[display(fmt = "{}", _0)]
+    Forbidden(String),
+    #[display(fmt = "Database is unavailable, please try again later")]
+    Database(mysql_async::Error),
+    #[display(fmt = "Server is shutting down, please try again later")]
+    ShuttingDown,
+    #[display(fmt = "{}", _0)]
+    Internal(String),
+}
+
+//single failing field of a request, e.g. {"field": "pcs", "code": "range", "message": "PCS must be between 1 and 10000"}
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct FieldError{
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: &str) -> FieldError {
+        FieldError { field: field.to_string(), code: code.to_string(), message: message.to_string() }
+    }
+}
+
+//JSON body of an error response
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorBody{
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InsufficientStock(_) => "insufficient_stock",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::MalformedRequest(_) => "malformed_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::Database(_) => "database_unavailable",
+            ApiError::ShuttingDown => "shutting_down",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    //request that is valid on its own but does not fit the stored data, e.g. a serial number already in stock
+    pub fn invalid_input(message: &str) -> ApiError {
+        ApiError::Validation(vec![FieldError::new("request", "invalid_input", message)])
+    }
+
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            ApiError::Validation(errors) | ApiError::MalformedRequest(errors) => errors.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InsufficientStock(_) => StatusCode::CONFLICT,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::MalformedRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Database(_) | ApiError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // logged inside the request span, so the request id is attached
+        if self.status_code().is_server_error() {
+            tracing::error!(code = self.code(), error = %self, "request failed");
+        } else {
+            tracing::debug!(code = self.code(), error = %self, "request rejected");
+        }
+
+        HttpResponse::build(self.status_code()).json(ErrorBody { code: self.code(), message: self.to_string(), errors: self.field_errors() })
+    }
+}
+
+// MySQL error codes caused by the request rather than the database
+const ER_DUP_ENTRY: u16 = 1062;
+const ER_NO_REFERENCED_ROW_2: u16 = 1452;
+
+// Duplicate keys and unknown references are the client's to fix, anything else is a database failure
+impl From<mysql_async::Error> for ApiError {
+    fn from(error: mysql_async::Error) -> Self {
+        match &error {
+            mysql_async::Error::Server(server_error) if server_error.code == ER_DUP_ENTRY => {
+                ApiError::Conflict("A record with the same key already exists".to_string())
+            }
+            mysql_async::Error::Server(server_error) if server_error.code == ER_NO_REFERENCED_ROW_2 => {
+                ApiError::Validation(vec![FieldError::new("request", "unknown_reference", "The request refers to a record that does not exist")])
+            }
+            _ => ApiError::Database(error),
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut field_errors = Vec::new();
+        collect_field_errors("", &errors, &mut field_errors);
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+        ApiError::Validation(field_errors)
+    }
+}
+
+//flattens nested errors into field paths such as lines[0].pcs, struct level errors are reported on the struct itself
+fn collect_field_errors(prefix: &str, errors: &ValidationErrors, field_errors: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = match (*field, prefix.is_empty()) {
+            ("__all__", true) => "request".to_string(),
+            ("__all__", false) => prefix.to_string(),
+            (field, true) => field.to_string(),
+            (field, false) => format!("{}.{}", prefix, field),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let message = error.message.as_deref().unwrap_or(&error.code);
+                    field_errors.push(FieldError::new(&path, &error.code, message));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(&path, nested, field_errors),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(&format!("{}[{}]", path, index), nested, field_errors);
+                }
+            }
+        }
+    }
+}
+
+//malformed JSON bodies answer with the same error shape as failed validation
+pub fn json_config() -> JsonConfig {
+    JsonConfig::default().error_handler(|error, _request| {
+        let field_error = match &error {
+            JsonPayloadError::Deserialize(serde_error) if serde_error.is_data() => FieldError::new("body", "invalid_value", &serde_error.to_string()),
+            JsonPayloadError::ContentType => FieldError::new("body", "content_type", "Content type must be application/json"),
+            _ => FieldError::new("body", "invalid_json", &error.to_string()),
+        };
+        ApiError::MalformedRequest(vec![field_error]).into()
+    })
+}
+
+//path segments that can not be read, e.g. a text where a number is expected
+pub fn path_config() -> PathConfig {
+    PathConfig::default().error_handler(|error: PathError, _request| {
+        ApiError::MalformedRequest(vec![FieldError::new("path", "invalid_path", &error.to_string())]).into()
+    })
+}
+
+//query strings that can not be read, e.g. ?days=abc
+pub fn query_config() -> QueryConfig {
+    QueryConfig::default().error_handler(|error: QueryPayloadError, _request| {
+        ApiError::MalformedRequest(vec![FieldError::new("query", "invalid_query", &error.to_string())]).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+    use crate::models::returns::CreateReturnAuthorizationRequest;
+
+    #[test]
+    fn database_errors_are_classified_by_code() {
+        let server_error = |code: u16| mysql_async::Error::Server(mysql_async::ServerError { code, message: String::new(), state: "23000".to_string() });
+        let broken = mysql_async::Error::from(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "Connection refused"));
+
+        let duplicate = ApiError::from(server_error(1062));
+        assert_eq!(duplicate.status_code(), StatusCode::CONFLICT);
+        assert_eq!(duplicate.code(), "conflict");
+
+        assert_eq!(ApiError::from(server_error(1452)).status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(ApiError::from(server_error(1213)).code(), "database_unavailable");
+        assert_eq!(ApiError::from(broken).code(), "database_unavailable");
+        assert_eq!(ApiError::invalid_input("Serial numbers do not match pcs").field_errors(), vec![FieldError::new("request", "invalid_input", "Serial numbers do not match pcs")]);
+        assert_eq!(ApiError::InsufficientStock("Not enough pcs for deduction".to_string()).status_code(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn validation_errors_are_listed_per_field() {
+        let request: CreateReturnAuthorizationRequest = serde_json::from_str(
+            r#"{"rma_number": "", "sales_order_number": "SO-1", "customer": "Shop", "lines": [{"product_code": "806807071423", "pcs": 0}]}"#,
+        ).unwrap();
+
+        let error = ApiError::from(request.validate().unwrap_err());
+
+        assert_eq!(error.field_errors(), vec![
+            FieldError::new("lines[0].pcs", "range", "PCS must be between 1 and 10000"),
+            FieldError::new("rma_number", "length", "RMA number must be between 1 and 64 characters"),
+        ]);
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::NaiveDate;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::models::errors::{ApiError, FieldError};
+use crate::models::incoming::AddOrUpdateUniqueIdentifierRequest;
+use crate::models::outgoing::RemoveUniqueIdentifierRequest;
+use crate::models::products::{ProductRef, Products};
+use crate::models::packaging::Unit;
+use crate::models::stock_status::StockStatus;
+
+// FNC1 is transmitted by scanners as the ASCII group separator
+const GROUP_SEPARATOR: char = '\u{1D}';
+// Symbology identifiers scanners may prepend to GS1-128 and GS1 DataMatrix data
+const SYMBOLOGY_IDENTIFIERS: [&str; 3] = ["]C1", "]d2", "]Q3"];
+
+#[derive(Debug, Display, PartialEq)]
+pub enum Gs1Error {
+    #[display(fmt = "Scan is empty")]
+    Empty,
+    #[display(fmt = "Scan contains characters GS1 does not use")]
+    NotAscii,
+    #[display(fmt = "Unsupported application identifier at position {}", _0)]
+    UnknownApplicationIdentifier(usize),
+    #[display(fmt = "Invalid value for application identifier ({})", _0)]
+    InvalidValue(&'static str),
+    #[display(fmt = "Application identifier ({}) appears more than once", _0)]
+    Duplicate(&'static str),
+}
+
+impl From<Gs1Error> for ApiError {
+    fn from(error: Gs1Error) -> Self {
+        ApiError::MalformedRequest(vec![FieldError::new("raw", "invalid_gs1", &error.to_string())])
+    }
+}
+
+//fields parsed from a raw GS1-128 scan
+#[derive(Serialize, Debug, Default, PartialEq, ToSchema)]
+pub struct Gs1Scan{
+    pub gtin: Option<String>,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub serial_number: Option<String>,
+    pub count: Option<i32>,
+}
+
+//request carrying the raw scanner input, warehouse and location are used to prefill requests
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct ScanRequest{
+    #[validate(length(min =1, max = 512, message = "Scan must be between 1 and 512 characters"))]
+    pub raw: String,
+    pub warehouse: Option<String>,
+    pub location: Option<String>,
+}
+
+//parsed scan with the resolved product and requests ready to be sent
+#[derive(Serialize, ToSchema)]
+pub struct ScanResult{
+    pub scan: Gs1Scan,
+    pub product: Option<Products>,
+    pub add_request: Option<AddOrUpdateUniqueIdentifierRequest>,
+    pub remove_request: Option<RemoveUniqueIdentifierRequest>,
+}
+
+impl ScanResult {
+    //prefills add and remove requests from the scan once the GTIN is resolved to a product
+    pub fn new(scan: Gs1Scan, product: Option<Products>, warehouse: Option<String>, location: Option<String>) -> Self {
+        let (add_request, remove_request) = match &product {
+            Some(product) => {
+                let warehouse = warehouse.unwrap_or_default();
+                let location = location.unwrap_or_default();
+                let pcs = scan.count.unwrap_or(1);
+                let serial_numbers = scan.serial_number.clone().map(|serial_number| vec![serial_number]);
+
+                let add_request = AddOrUpdateUniqueIdentifierRequest {
+                    product: ProductRef::by_code(&product.product_code),
+                    warehouse: warehouse.clone(),
+                    location: location.clone(),
+                    pcs,
+                    unit: Unit::Pcs,
+                    lot_number: scan.lot_number.clone(),
+                    expiry_date: scan.expiry_date,
+                    status: StockStatus::Available,
+                    serial_numbers: serial_numbers.clone(),
+                    unit_cost: None,
+                };
+
+                let remove_request = RemoveUniqueIdentifierRequest {
+                    product: ProductRef::by_code(&product.product_code),
+                    warehouse,
+                    location,
+                    pcs,
+                    unit: Unit::Pcs,
+                    lot_number: scan.lot_number.clone(),
+                    expiry_date: scan.expiry_date,
+                    status: StockStatus::Available,
+                    serial_numbers,
+                    customer: None,
+                };
+
+                (Some(add_request), Some(remove_request))
+            }
+            None => (None, None),
+        };
+
+        ScanResult { scan, product, add_request, remove_request }
+    }
+}
+
+// Supported application identifiers: (AI, fixed length or None for variable, max length)
+const APPLICATION_IDENTIFIERS: [(&str, Option<usize>, usize); 5] = [
+    ("01", Some(14), 14),
+    ("10", None, 20),
+    ("17", Some(6), 6),
+    ("21", None, 20),
+    ("30", None, 8),
+];
+
+//parses both raw scanner output (FNC1 as GS) and the human readable "(01)...(10)..." form
+pub fn parse_gs1(raw: &str) -> Result<Gs1Scan, Gs1Error> {
+    // GS1 element strings are ASCII, so byte offsets below are always character boundaries
+    if !raw.is_ascii() {
+        return Err(Gs1Error::NotAscii);
+    }
+
+    let mut data = raw.trim();
+    for identifier in SYMBOLOGY_IDENTIFIERS {
+        if let Some(stripped) = data.strip_prefix(identifier) {
+            data = stripped;
+        }
+    }
+    let data = data.trim_start_matches(GROUP_SEPARATOR);
+
+    if data.is_empty() {
+        return Err(Gs1Error::Empty);
+    }
+
+    // Human readable form is turned into the raw form, brackets delimit every element
+    let data = if data.starts_with('(') {
+        data.replace(')', "").split('(').filter(|element| !element.is_empty()).collect::<Vec<_>>().join(&GROUP_SEPARATOR.to_string())
+    } else {
+        data.to_string()
+    };
+
+    let mut scan = Gs1Scan::default();
+    let mut position = 0;
+
+    while position < data.len() {
+        let rest = &data[position..];
+        let (ai, fixed_length, max_length) = APPLICATION_IDENTIFIERS
+            .iter()
+            .find(|(ai, _, _)| rest.starts_with(ai))
+            .copied()
+            .ok_or(Gs1Error::UnknownApplicationIdentifier(position))?;
+
+        let value_start = position + ai.len();
+        let value_end = match fixed_length {
+            Some(length) => value_start + length,
+            None => data[value_start..].find(GROUP_SEPARATOR).map(|index| value_start + index).unwrap_or(data.len()),
+        };
+
+        if value_end > data.len() || value_end - value_start > max_length || value_end == value_start {
+            return Err(Gs1Error::InvalidValue(ai));
+        }
+
+        let value = &data[value_start..value_end];
+        apply_element(&mut scan, ai, value)?;
+
+        // Skip the separator after variable length elements (also tolerated after fixed ones)
+        position = value_end;
+        if data[position..].starts_with(GROUP_SEPARATOR) {
+            position += GROUP_SEPARATOR.len_utf8();
+        }
+    }
+
+    Ok(scan)
+}
+
+fn apply_element(scan: &mut Gs1Scan, ai: &'static str, value: &str) -> Result<(), Gs1Error> {
+    match ai {
+        "01" => {
+            if !value.chars().all(|character| character.is_ascii_digit()) {
+                return Err(Gs1Error::InvalidValue(ai));
+            }
+            set_once(&mut scan.gtin, value.to_string(), ai)
+        }
+        "10" => set_once(&mut scan.lot_number, value.to_string(), ai),
+        "17" => set_once(&mut scan.expiry_date, parse_gs1_date(value).ok_or(Gs1Error::InvalidValue(ai))?, ai),
+        "21" => set_once(&mut scan.serial_number, value.to_string(), ai),
+        "30" => set_once(&mut scan.count, value.parse().map_err(|_| Gs1Error::InvalidValue(ai))?, ai),
+        _ => Err(Gs1Error::InvalidValue(ai)),
+    }
+}
+
+fn set_once<T>(field: &mut Option<T>, value: T, ai: &'static str) -> Result<(), Gs1Error> {
+    if field.is_some() {
+        return Err(Gs1Error::Duplicate(ai));
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+// YYMMDD, day 00 means the last day of the month
+fn parse_gs1_date(value: &str) -> Option<NaiveDate> {
+    if value.len() != 6 || !value.chars().all(|character| character.is_ascii_digit()) {
+        return None;
+    }
+
+    let year = 2000 + value[0..2].parse::<i32>().ok()?;
+    let month = value[2..4].parse::<u32>().ok()?;
+    let day = value[4..6].parse::<u32>().ok()?;
+
+    if day == 0 {
+        let first_of_next_month = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)?
+        };
+        return first_of_next_month.pred_opt();
+    }
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+//product codes a GTIN-14 may be stored as (GTIN-14, EAN-13, UPC-A, GTIN-8)
+pub fn gtin_candidates(gtin: &str) -> Vec<String> {
+    if gtin.len() != 14 {
+        return vec![gtin.to_string()];
+    }
+
+    let mut candidates = vec![gtin.to_string()];
+    for padding in [1, 2, 6] {
+        if gtin[..padding].chars().all(|character| character == '0') {
+            candidates.push(gtin[padding..].to_string());
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_raw_scan_with_group_separators() {
+        let scan = parse_gs1("]C10100806807071423172503001012345\u{1D}3024").unwrap();
+
+        assert_eq!(scan.gtin.as_deref(), Some("00806807071423"));
+        assert_eq!(scan.expiry_date, NaiveDate::from_ymd_opt(2025, 3, 31));
+        assert_eq!(scan.lot_number.as_deref(), Some("12345"));
+        assert_eq!(scan.count, Some(24));
+    }
+
+    #[test]
+    fn parses_human_readable_scan() {
+        let scan = parse_gs1("(01)00806807071423(10)L-42(17)250315").unwrap();
+
+        assert_eq!(scan.lot_number.as_deref(), Some("L-42"));
+        assert_eq!(scan.expiry_date, NaiveDate::from_ymd_opt(2025, 3, 15));
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers_and_bad_values() {
+        assert_eq!(parse_gs1("9912345"), Err(Gs1Error::UnknownApplicationIdentifier(0)));
+        assert_eq!(parse_gs1("01008068070714"), Err(Gs1Error::InvalidValue("01")));
+        assert_eq!(parse_gs1("17251340"), Err(Gs1Error::InvalidValue("17")));
+    }
+
+    #[test]
+    fn rejects_non_ascii_scans() {
+        assert_eq!(parse_gs1("01éééééé0é"), Err(Gs1Error::NotAscii));
+        assert_eq!(parse_gs1("(01)00806807071423(10)Š-42"), Err(Gs1Error::NotAscii));
+    }
+
+    #[test]
+    fn gtin_candidates_strip_leading_zeros() {
+        assert_eq!(gtin_candidates("00806807071423"), vec!["00806807071423", "0806807071423", "806807071423"]);
+    }
+}
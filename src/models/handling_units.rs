@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::NaiveDate;
+use derive_more::Display;
+
+use crate::db::database::build_concatenated_string;
+use crate::models::incoming::{take_column, UniqueIdentifier};
+use crate::models::products::{parse_attributes, ProductRef};
+
+//type of a handling unit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HandlingUnitType {
+    Pallet,
+    Carton,
+}
+
+impl HandlingUnitType {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            HandlingUnitType::Pallet => "PALLET",
+            HandlingUnitType::Carton => "CARTON",
+        }
+    }
+}
+
+//single handling unit row
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HandlingUnit{
+    pub lpn: String,
+    pub parent_lpn: Option<String>,
+    pub unit_type: String,
+    pub warehouse: String,
+    pub location: String,
+}
+
+impl FromRow for HandlingUnit {
+    fn from_row(row: Row) -> Self{
+
+        let (lpn, parent_lpn, unit_type, warehouse, location):(String, Option<String>, String, String, String) = mysql_async::from_row(row);
+
+        HandlingUnit{lpn, parent_lpn, unit_type, warehouse, location}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (lpn, parent_lpn, unit_type, warehouse, location):(String, Option<String>, String, String, String) = mysql_async::from_row(row);
+
+        Ok(HandlingUnit{lpn, parent_lpn, unit_type, warehouse, location})
+    }
+}
+
+//stock line packed in a handling unit
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HandlingUnitContent{
+    pub concatenated_string: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub attributes: BTreeMap<String, String>,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub pcs: i32,
+}
+
+impl FromRow for HandlingUnitContent {
+    fn from_row(row: Row) -> Self{
+
+        HandlingUnitContent::from_row_opt(row).expect("Could not convert row to HandlingUnitContent")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let mut columns = row.clone();
+
+        let content = (|| Some(HandlingUnitContent{
+            concatenated_string: take_column(&mut columns, "concatenated_string")?,
+            product_code: take_column(&mut columns, "product_code")?,
+            product_name: take_column(&mut columns, "product_name")?,
+            attributes: parse_attributes(take_column(&mut columns, "attributes")?)?,
+            lot_number: take_column(&mut columns, "lot_number")?,
+            expiry_date: take_column(&mut columns, "expiry_date")?,
+            pcs: take_column(&mut columns, "pcs")?,
+        }))();
+
+        content.ok_or(mysql_async::FromRowError(row))
+    }
+}
+
+//handling unit with its contents and nested handling units
+#[derive(Serialize, Debug, ToSchema)]
+pub struct HandlingUnitDetails{
+    #[serde(flatten)]
+    pub handling_unit: HandlingUnit,
+    pub contents: Vec<HandlingUnitContent>,
+    //nested units refer back to this schema
+    #[schema(no_recursion)]
+    pub children: Vec<HandlingUnitDetails>,
+}
+
+//get request for a single handling unit
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetHandlingUnit{
+    #[validate(length(min =1, message = "LPN is required"))]
+    pub lpn: String,
+}
+
+//request for creating an empty handling unit at a location
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct CreateHandlingUnitRequest{
+    #[validate(length(min =1, max = 64, message = "LPN must be between 1 and 64 characters"))]
+    pub lpn: String,
+    pub unit_type: HandlingUnitType,
+    #[validate(length(min =1, message = "Warehouse is required"))]
+    pub warehouse: String,
+    #[validate(length(min =1, message = "Location is required"))]
+    pub location: String,
+    #[validate(length(min =1, message = "Parent LPN can not be empty"))]
+    pub parent_lpn: Option<String>,
+}
+
+//request for packing loose stock at the handling unit location into it, or unpacking it again
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct PackHandlingUnitRequest{
+    #[validate(length(min =1, message = "LPN is required"))]
+    pub lpn: String,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub product: ProductRef,
+    #[validate(length(min =1, message = "Lot number can not be empty"))]
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
+    pub pcs: i32,
+}
+
+//request for nesting a handling unit into another one, or taking it out when parent_lpn is empty
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct NestHandlingUnitRequest{
+    #[validate(length(min =1, message = "LPN is required"))]
+    pub lpn: String,
+    #[validate(length(min =1, message = "Parent LPN can not be empty"))]
+    pub parent_lpn: Option<String>,
+}
+
+//request for moving a handling unit with everything in it to another location
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct MoveHandlingUnitRequest{
+    #[validate(length(min =1, message = "LPN is required"))]
+    pub lpn: String,
+    #[validate(length(min =1, message = "Warehouse is required"))]
+    pub warehouse: String,
+    #[validate(length(min =1, message = "Location is required"))]
+    pub location: String,
+}
+
+//reasons a pack, unpack, nest or move is refused, decided without the database
+#[derive(Debug, Display, PartialEq)]
+pub enum HandlingUnitError {
+    #[display(fmt = "Not enough loose pcs for packing, {} pcs are not packed", _0)]
+    NotEnoughLoosePcs(i32),
+    #[display(fmt = "Product is not packed in the handling unit")]
+    NotPacked,
+    #[display(fmt = "Not enough pcs in the handling unit, {} pcs are packed", _0)]
+    NotEnoughPackedPcs(i32),
+    #[display(fmt = "Parent handling unit is at another location")]
+    ParentElsewhere,
+    #[display(fmt = "Handling unit can not be nested into itself")]
+    NestedIntoItself,
+}
+
+//pcs of a stock row that are not packed in any handling unit
+pub fn loose_pcs(stock_pcs: i32, packed_pcs: i32) -> i32 {
+    (stock_pcs - packed_pcs).max(0)
+}
+
+// Only loose pcs can be packed, so the packed pcs of a row never exceed the pcs it holds
+pub fn check_pack(stock_pcs: i32, packed_pcs: i32, pcs: i32) -> Result<(), HandlingUnitError> {
+    let loose = loose_pcs(stock_pcs, packed_pcs);
+    if pcs > loose {
+        return Err(HandlingUnitError::NotEnoughLoosePcs(loose));
+    }
+
+    Ok(())
+}
+
+//pcs of the product packed in the handling unit, None when it is not packed there
+pub fn check_unpack(content_pcs: Option<i32>, pcs: i32) -> Result<(), HandlingUnitError> {
+    match content_pcs {
+        Some(packed) if packed >= pcs => Ok(()),
+        Some(packed) => Err(HandlingUnitError::NotEnoughPackedPcs(packed)),
+        None => Err(HandlingUnitError::NotPacked),
+    }
+}
+
+//a parent has to be at the location of the unit and must not be the unit or one of the units nested in it
+pub fn check_parent(warehouse: &str, location: &str, parent: &HandlingUnit, nested_lpns: &[String]) -> Result<(), HandlingUnitError> {
+    if parent.warehouse != warehouse || parent.location != location {
+        return Err(HandlingUnitError::ParentElsewhere);
+    }
+    if nested_lpns.contains(&parent.lpn) {
+        return Err(HandlingUnitError::NestedIntoItself);
+    }
+
+    Ok(())
+}
+
+//stock row the packed pcs of a moved handling unit go to, None when the move keeps them in the same row
+pub fn moved_row(source: &UniqueIdentifier, warehouse: &str, location: &str) -> Option<String> {
+    let target = build_concatenated_string(
+        &source.product_code, warehouse, location,
+        source.lot_number.as_deref(), source.expiry_date, source.status,
+    );
+
+    (target != source.concatenated_string).then_some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::stock_status::StockStatus;
+
+    fn unit(lpn: &str, warehouse: &str, location: &str) -> HandlingUnit {
+        HandlingUnit { lpn: lpn.to_string(), parent_lpn: None, unit_type: "PALLET".to_string(), warehouse: warehouse.to_string(), location: location.to_string() }
+    }
+
+    #[test]
+    fn packed_pcs_never_exceed_the_row() {
+        assert_eq!(check_pack(10, 4, 6), Ok(()));
+        assert_eq!(check_pack(10, 4, 7), Err(HandlingUnitError::NotEnoughLoosePcs(6)));
+        assert_eq!(check_pack(10, 10, 1), Err(HandlingUnitError::NotEnoughLoosePcs(0)));
+        // Rows that lost pcs behind the packing never report negative loose pcs
+        assert_eq!(loose_pcs(3, 5), 0);
+    }
+
+    #[test]
+    fn unpack_takes_at_most_the_packed_pcs() {
+        assert_eq!(check_unpack(Some(5), 5), Ok(()));
+        assert_eq!(check_unpack(Some(5), 6), Err(HandlingUnitError::NotEnoughPackedPcs(5)));
+        assert_eq!(check_unpack(None, 1), Err(HandlingUnitError::NotPacked));
+    }
+
+    #[test]
+    fn units_are_nested_at_the_same_location_without_cycles() {
+        let pallet = unit("PAL-1", "HALA 5", "M5-A-1");
+        let nested = vec!["CAR-1".to_string(), "CAR-2".to_string()];
+
+        assert_eq!(check_parent("HALA 5", "M5-A-1", &pallet, &nested), Ok(()));
+        assert_eq!(check_parent("HALA 5", "M5-A-2", &pallet, &nested), Err(HandlingUnitError::ParentElsewhere));
+        assert_eq!(check_parent("HALA 1", "M5-A-1", &pallet, &nested), Err(HandlingUnitError::ParentElsewhere));
+        assert_eq!(check_parent("HALA 5", "M5-A-1", &unit("CAR-2", "HALA 5", "M5-A-1"), &nested), Err(HandlingUnitError::NestedIntoItself));
+    }
+
+    #[test]
+    fn moved_contents_follow_the_unit_to_its_new_row() {
+        let source = UniqueIdentifier {
+            concatenated_string: "806807071423^HALA 5^M5-A-1^L-42^".to_string(),
+            product_code: "806807071423".to_string(),
+            product_name: "Smart thermostat".to_string(),
+            attributes: BTreeMap::new(),
+            warehouse: "HALA 5".to_string(),
+            location: "M5-A-1".to_string(),
+            pcs: 12,
+            lot_number: Some("L-42".to_string()),
+            expiry_date: None,
+            status: StockStatus::Available,
+            emptied_at: None,
+            breakdown: None,
+        };
+
+        assert_eq!(moved_row(&source, "HALA 1", "M1-B-3"), Some("806807071423^HALA 1^M1-B-3^L-42^".to_string()));
+        assert_eq!(moved_row(&source, "HALA 5", "M5-A-1"), None);
+    }
+}
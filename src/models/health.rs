@@ -0,0 +1,53 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::pool::PoolStats;
+
+//GET /readyz body, ready only when the database answers and has the expected schema
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Readiness{
+    pub ready: bool,
+    pub database: DatabaseCheck,
+    pub schema: SchemaCheck,
+    pub pool: PoolStats,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DatabaseCheck{
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+//schema version of the migrations in "SQL code.txt" against the one the server was built for
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SchemaCheck{
+    pub expected: u32,
+    pub found: Option<u32>,
+}
+
+impl Readiness {
+    pub fn new(database: DatabaseCheck, schema: SchemaCheck, pool: PoolStats) -> Readiness {
+        let ready = database.reachable && schema.found == Some(schema.expected);
+        Readiness { ready, database, schema, pool }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_only_with_database_and_matching_schema() {
+        let pool = PoolStats { min: 10, max: 100, in_use: 1, waiting: 0 };
+        let reachable = || DatabaseCheck { reachable: true, latency_ms: Some(1), error: None };
+
+        assert!(Readiness::new(reachable(), SchemaCheck { expected: 1, found: Some(1) }, pool.clone()).ready);
+        assert!(!Readiness::new(reachable(), SchemaCheck { expected: 2, found: Some(1) }, pool.clone()).ready);
+
+        let down = DatabaseCheck { reachable: false, latency_ms: None, error: Some("database unavailable".to_string()) };
+        assert!(!Readiness::new(down, SchemaCheck { expected: 1, found: None }, pool).ready);
+    }
+}
@@ -1,50 +1,110 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
-use mysql_async::prelude::FromRow;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use mysql_async::prelude::{FromRow, FromValue};
 use mysql_async::Row;
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::models::packaging::{QuantityBreakdown, Unit};
+use crate::models::stock_status::StockStatus;
+use crate::models::products::{parse_attributes, ProductRef};
 
-//initializaing unique identifier object
-#[derive(Serialize, Deserialize, Debug)]
+//initializaing unique identifier object. product_name and attributes are not stored
+//on the stock row, they are read from the product variant
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UniqueIdentifier{
     pub concatenated_string: String,
     pub product_code: String,
-    pub color: String,
     pub product_name: String,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
     pub warehouse: String,
     pub location: String,
     pub pcs: i32,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub status: StockStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emptied_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<QuantityBreakdown>,
 
 }
 
 impl FromRow for UniqueIdentifier {
     fn from_row(row: Row) -> Self{
 
-        let (concatenated_string, product_code, color, product_name, warehouse, location, pcs):(String, String,String, String, String, String, i32) = mysql_async::from_row(row);
-
-        UniqueIdentifier{concatenated_string, product_code, color, product_name,warehouse,location,pcs}
+        UniqueIdentifier::from_row_opt(row).expect("Could not convert row to UniqueIdentifier")
     }
 
     fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
-        let (concatenated_string, product_code,color, product_name, warehouse, location, pcs):(String, String,String, String, String, String, i32) = mysql_async::from_row(row);
+        // Columns are read by name, so SELECT * keeps working as columns are added to the table
+        let mut columns = row.clone();
+
+        let unique_identifier = (|| Some(UniqueIdentifier{
+            concatenated_string: take_column(&mut columns, "concatenated_string")?,
+            product_code: take_column(&mut columns, "product_code")?,
+            product_name: take_column(&mut columns, "product_name")?,
+            attributes: parse_attributes(take_column(&mut columns, "attributes")?)?,
+            warehouse: take_column(&mut columns, "warehouse")?,
+            location: take_column(&mut columns, "location")?,
+            pcs: take_column(&mut columns, "pcs")?,
+            lot_number: take_column(&mut columns, "lot_number")?,
+            expiry_date: take_column(&mut columns, "expiry_date")?,
+            // An unknown status fails the row instead of being offered as available stock
+            status: StockStatus::from_db_str(&take_column::<String>(&mut columns, "status")?)?,
+            emptied_at: take_column(&mut columns, "emptied_at")?,
+            breakdown: None,
+        }))();
 
-        Ok(UniqueIdentifier{concatenated_string, product_code,color, product_name,warehouse,location,pcs})
+        unique_identifier.ok_or(mysql_async::FromRowError(row))
     }
 }
 
+//value of a named column, None when the column is missing or has another type
+pub(crate) fn take_column<T: FromValue>(row: &mut Row, name: &str) -> Option<T> {
+    row.take_opt(name)?.ok()
+}
+
 
 //adding unique identifier request
-#[derive(Validate, Deserialize, Serialize)]
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
 pub struct AddOrUpdateUniqueIdentifierRequest{
-    #[validate(length(min =1, message = "Color is required"))]
-    pub color: String,
-    #[validate(length(min =1, message = "Product name is required"))]
-    pub product_name: String,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub product: ProductRef,
     #[validate(length(min =1, message = "Warehouse is required"))]
     pub warehouse: String,
     #[validate(length(min =1, message = "Location is required"))]
     pub location: String,
     #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
     pub pcs: i32,
+    #[serde(default)]
+    pub unit: Unit,
+    #[validate(length(min =1, message = "Lot number can not be empty"))]
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub status: StockStatus,
+    #[validate(length(min =1, message = "Serial numbers can not be an empty list"))]
+    pub serial_numbers: Option<Vec<String>>,
+    #[validate(custom(function = "validate_unit_cost"))]
+    pub unit_cost: Option<Decimal>,
+}
+
+// DECIMAL(14, 4) column of cost_movements
+pub(crate) fn validate_unit_cost(unit_cost: &Option<Decimal>) -> Result<(), ValidationError> {
+    match unit_cost {
+        Some(unit_cost) if unit_cost.is_sign_negative() || unit_cost.scale() > 4 || *unit_cost >= Decimal::new(10_000_000_000, 0) => {
+            let mut error = ValidationError::new("unit_cost");
+            error.message = Some(Cow::from("Unit cost must be between 0 and 9999999999.9999 with at most 4 decimals"));
+            Err(error)
+        }
+        _ => Ok(()),
+    }
 }
 
 // ---- TEST 1.c ------------- //
@@ -59,7 +119,7 @@ pub struct GetProductLocationsByCode{
 }
 
 //adding get request for product_code and pcs for sales order
-#[derive(Serialize,Deserialize,Validate)]
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
 pub struct GetCodeAndPcsForSalesOrder{
     #[validate(length(min =1, message = "Product Code is required"))]
     pub product_code: String,
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::labels::{LabelFormat, LocationSymbology};
+
+// Upper limit of labels rendered in one batch
+pub const MAX_LABELS_PER_BATCH: i32 = 500;
+
+// Longest warehouse and location whose Code128 still fits the width of a location label
+const MAX_WAREHOUSE_LENGTH: u64 = 32;
+const MAX_LOCATION_LENGTH: u64 = 32;
+// Leaves room for the location number, at most 6 digits
+const MAX_LOCATION_PREFIX_LENGTH: u64 = MAX_LOCATION_LENGTH - 6;
+
+//query string shared by all label endpoints
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LabelQuery{
+    #[serde(default)]
+    pub format: LabelFormat,
+    #[serde(default)]
+    pub symbology: LocationSymbology,
+}
+
+//get request for a product label
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetProductLabel{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+}
+
+//get request for a single location label
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetLocationLabel{
+    #[validate(length(min =1, max = MAX_WAREHOUSE_LENGTH, message = "Warehouse must be between 1 and 32 characters"))]
+    pub warehouse: String,
+    #[validate(length(min =1, max = MAX_LOCATION_LENGTH, message = "Location must be between 1 and 32 characters"))]
+    pub location: String,
+}
+
+//batch request for labels of a location range, e.g. M5-A-1 .. M5-A-10
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+#[validate(schema(function = "validate_location_range"))]
+pub struct LocationRangeLabelRequest{
+    #[validate(length(min =1, max = MAX_WAREHOUSE_LENGTH, message = "Warehouse must be between 1 and 32 characters"))]
+    pub warehouse: String,
+    #[validate(length(min =1, max = MAX_LOCATION_PREFIX_LENGTH, message = "Location prefix must be between 1 and 26 characters"))]
+    pub location_prefix: String,
+    #[validate(range(min = 0, max = 100000, message = "From must be between 0 and 100000"))]
+    pub from: i32,
+    #[validate(range(min = 0, max = 100000, message = "To must be between 0 and 100000"))]
+    pub to: i32,
+    #[serde(default)]
+    pub format: LabelFormat,
+    #[serde(default)]
+    pub symbology: LocationSymbology,
+}
+
+impl LocationRangeLabelRequest {
+    pub fn locations(&self) -> Vec<String> {
+        (self.from..=self.to)
+            .map(|number| format!("{}{}", self.location_prefix, number))
+            .collect()
+    }
+}
+
+fn validate_location_range(request: &LocationRangeLabelRequest) -> Result<(), ValidationError> {
+    if request.to < request.from || request.to - request.from >= MAX_LABELS_PER_BATCH {
+        let mut error = ValidationError::new("location_range");
+        error.message = Some(Cow::from("Location range must be ascending and contain at most 500 locations"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_too_long_for_a_barcode_are_rejected() {
+        let label = GetLocationLabel { warehouse: "HALA 5".to_string(), location: "M".repeat(90) };
+        assert!(label.validate().is_err());
+
+        let range = LocationRangeLabelRequest {
+            warehouse: "HALA 5".to_string(),
+            location_prefix: "M5-A-".to_string(),
+            from: 1,
+            to: 10,
+            format: LabelFormat::default(),
+            symbology: LocationSymbology::default(),
+        };
+        assert!(range.validate().is_ok());
+        assert!(LocationRangeLabelRequest { location_prefix: "M".repeat(27), ..range }.validate().is_err());
+    }
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use chrono::NaiveDate;
+
+//get request for stock that expires within the given number of days
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetExpiringStock{
+    #[validate(range(min = 0, max = 3650, message = "Days must be between 0 and 3650"))]
+    pub days: i32,
+}
+
+//single pick line returned by FEFO allocation
+#[derive(Serialize, Debug, ToSchema)]
+pub struct StockAllocation{
+    pub concatenated_string: String,
+    pub warehouse: String,
+    pub location: String,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub pcs: i32,
+}
@@ -1,3 +1,20 @@
 pub mod incoming;
 pub mod outgoing;
 pub mod products;
+pub mod product_code;
+pub mod gs1;
+pub mod lots;
+pub mod serials;
+pub mod labels;
+pub mod packaging;
+pub mod handling_units;
+pub mod stock_status;
+pub mod returns;
+pub mod valuation;
+pub mod reports;
+pub mod abc;
+pub mod tenant;
+pub mod empty_stock;
+pub mod errors;
+pub mod health;
+pub mod users;
\ No newline at end of file
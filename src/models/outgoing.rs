@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
+use chrono::NaiveDate;
+use crate::models::packaging::Unit;
+use crate::models::stock_status::StockStatus;
+use crate::models::products::ProductRef;
 
 // TEST 3 //
 //Uncoment imports bellow //
@@ -21,16 +26,26 @@ pub struct OutgoingIdentifier{
 
 
 //adding unique outgoing identifier request
-#[derive(Validate, Deserialize, Serialize)]
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
 pub struct RemoveUniqueIdentifierRequest{
-    #[validate(length(min =1, message = "Color is required"))]
-    pub color: String,
-    #[validate(length(min =1, message = "Product name is required"))]
-    pub product_name: String,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub product: ProductRef,
     #[validate(length(min =1, message = "Warehouse is required"))]
     pub warehouse: String,
     #[validate(length(min =1, message = "Location is required"))]
     pub location: String,
     #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
     pub pcs: i32,
+    #[serde(default)]
+    pub unit: Unit,
+    #[validate(length(min =1, message = "Lot number can not be empty"))]
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub status: StockStatus,
+    #[validate(length(min =1, message = "Serial numbers can not be an empty list"))]
+    pub serial_numbers: Option<Vec<String>>,
+    #[validate(length(min =1, message = "Customer can not be empty"))]
+    pub customer: Option<String>,
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+
+
+//unit of measure a quantity can be given in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    #[default]
+    Pcs,
+    Box,
+    Pallet,
+}
+
+impl Unit {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Unit::Pcs => "PCS",
+            Unit::Box => "BOX",
+            Unit::Pallet => "PALLET",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Option<Unit> {
+        match value {
+            "PCS" => Some(Unit::Pcs),
+            "BOX" => Some(Unit::Box),
+            "PALLET" => Some(Unit::Pallet),
+            _ => None,
+        }
+    }
+}
+
+//single packaging definition row, e.g. 1 PALLET = 40 BOX
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PackagingDefinition{
+    pub product_code: String,
+    pub unit: String,
+    pub quantity: i32,
+    pub contains_unit: String,
+}
+
+impl FromRow for PackagingDefinition {
+    fn from_row(row: Row) -> Self{
+
+        let (product_code, unit, quantity, contains_unit):(String, String, i32, String) = mysql_async::from_row(row);
+
+        PackagingDefinition{product_code, unit, quantity, contains_unit}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (product_code, unit, quantity, contains_unit):(String, String, i32, String) = mysql_async::from_row(row);
+
+        Ok(PackagingDefinition{product_code, unit, quantity, contains_unit})
+    }
+}
+
+//request for defining how many of contains_unit are packed in one unit
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct SetPackagingRequest{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    pub unit: Unit,
+    #[validate(range(min = 1, max = 100000, message = "Quantity must be between 1 and 100000"))]
+    pub quantity: i32,
+    pub contains_unit: Unit,
+}
+
+//get request for packaging definitions of a product
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetPackaging{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+}
+
+//query string for stock endpoints
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StockQuery{
+    #[serde(default)]
+    pub breakdown: bool,
+}
+
+//quantity split into full pallets, full boxes and loose pieces
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+pub struct QuantityBreakdown{
+    pub pallets: i32,
+    pub boxes: i32,
+    pub pieces: i32,
+}
+
+//packaging of one product resolved to pieces
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Packaging{
+    pub pcs_per_box: Option<i32>,
+    pub pcs_per_pallet: Option<i32>,
+}
+
+impl Packaging {
+    //resolves definitions of one product, pallets may be defined in boxes or in pieces
+    pub fn from_definitions(definitions: &[PackagingDefinition]) -> Packaging {
+        let find = |unit: Unit| definitions.iter().find(|definition| Unit::from_db_str(&definition.unit) == Some(unit));
+
+        let pcs_per_box = find(Unit::Box)
+            .filter(|definition| Unit::from_db_str(&definition.contains_unit) == Some(Unit::Pcs))
+            .map(|definition| definition.quantity);
+
+        let pcs_per_pallet = find(Unit::Pallet).and_then(|definition| match Unit::from_db_str(&definition.contains_unit) {
+            Some(Unit::Pcs) => Some(definition.quantity),
+            Some(Unit::Box) => pcs_per_box.and_then(|pcs| pcs.checked_mul(definition.quantity)),
+            _ => None,
+        });
+
+        Packaging { pcs_per_box, pcs_per_pallet }
+    }
+
+    //converts a quantity in the given unit to pieces, None if the unit is not defined for the product
+    pub fn pcs_in(&self, quantity: i32, unit: Unit) -> Option<i32> {
+        match unit {
+            Unit::Pcs => Some(quantity),
+            Unit::Box => self.pcs_per_box.and_then(|pcs| pcs.checked_mul(quantity)),
+            Unit::Pallet => self.pcs_per_pallet.and_then(|pcs| pcs.checked_mul(quantity)),
+        }
+    }
+
+    pub fn breakdown(&self, pcs: i32) -> QuantityBreakdown {
+        let mut rest = pcs;
+
+        let pallets = match self.pcs_per_pallet {
+            Some(per_pallet) if per_pallet > 0 => rest / per_pallet,
+            _ => 0,
+        };
+        rest -= pallets * self.pcs_per_pallet.unwrap_or(0);
+
+        let boxes = match self.pcs_per_box {
+            Some(per_box) if per_box > 0 => rest / per_box,
+            _ => 0,
+        };
+        rest -= boxes * self.pcs_per_box.unwrap_or(0);
+
+        QuantityBreakdown { pallets, boxes, pieces: rest }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(unit: &str, quantity: i32, contains_unit: &str) -> PackagingDefinition {
+        PackagingDefinition { product_code: "806807071423".to_string(), unit: unit.to_string(), quantity, contains_unit: contains_unit.to_string() }
+    }
+
+    #[test]
+    fn converts_and_breaks_down_through_the_hierarchy() {
+        let packaging = Packaging::from_definitions(&[definition("BOX", 12, "PCS"), definition("PALLET", 40, "BOX")]);
+
+        assert_eq!(packaging.pcs_per_pallet, Some(480));
+        assert_eq!(packaging.pcs_in(2, Unit::Pallet), Some(960));
+        assert_eq!(packaging.pcs_in(3, Unit::Box), Some(36));
+        assert_eq!(packaging.breakdown(1000), QuantityBreakdown { pallets: 2, boxes: 3, pieces: 4 });
+    }
+
+    #[test]
+    fn undefined_units_can_not_be_converted() {
+        let packaging = Packaging::from_definitions(&[]);
+
+        assert_eq!(packaging.pcs_in(1, Unit::Box), None);
+        assert_eq!(packaging.breakdown(7), QuantityBreakdown { pallets: 0, boxes: 0, pieces: 7 });
+    }
+}
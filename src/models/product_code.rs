@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+
+use validator::ValidationError;
+
+// GTIN-8, UPC-A (GTIN-12), EAN-13 and GTIN-14 are the lengths GS1 issues
+pub const GTIN_LENGTHS: [usize; 4] = [8, 12, 13, 14];
+
+//GS1 check digit for the digits in front of it (weights 3 and 1 from the right)
+pub fn gs1_check_digit(body: &str) -> Option<u32> {
+    let mut sum = 0;
+
+    for (position, character) in body.chars().rev().enumerate() {
+        let digit = character.to_digit(10)?;
+        sum += if position % 2 == 0 { digit * 3 } else { digit };
+    }
+
+    Some((10 - sum % 10) % 10)
+}
+
+pub fn is_valid_gtin(code: &str) -> bool {
+    if !GTIN_LENGTHS.contains(&code.len()) || !code.chars().all(|character| character.is_ascii_digit()) {
+        return false;
+    }
+
+    let (body, check) = code.split_at(code.len() - 1);
+    gs1_check_digit(body) == check.chars().next().and_then(|character| character.to_digit(10))
+}
+
+//custom validator for the code of a new product. Requests that refer to an existing
+//product only need a non-empty code, codes stored before the check was added stay valid
+pub fn validate_product_code(code: &str) -> Result<(), ValidationError> {
+    if !GTIN_LENGTHS.contains(&code.len()) || !code.chars().all(|character| character.is_ascii_digit()) {
+        let mut error = ValidationError::new("product_code_length");
+        error.message = Some(Cow::from("Product Code must be 8, 12, 13 or 14 digits"));
+        return Err(error);
+    }
+
+    if !is_valid_gtin(code) {
+        let mut error = ValidationError::new("product_code_check_digit");
+        error.message = Some(Cow::from("Product Code has an invalid check digit"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+//same as validate_product_code, for models where the product_code can be omitted
+pub fn validate_optional_product_code(code: &Option<String>) -> Result<(), ValidationError> {
+    match code {
+        Some(code) => validate_product_code(code),
+        None => Ok(()),
+    }
+}
+
+//company prefix of digits that leaves room for at least one reference digit and the check digit
+pub fn is_valid_company_prefix(company_prefix: &str, code_length: usize) -> bool {
+    GTIN_LENGTHS.contains(&code_length)
+        && !company_prefix.is_empty()
+        && company_prefix.len() + 2 <= code_length
+        && company_prefix.chars().all(|character| character.is_ascii_digit())
+}
+
+//next free code in the company prefix, given the codes already issued under that prefix
+pub fn next_product_code(company_prefix: &str, code_length: usize, existing_codes: &[String]) -> Option<String> {
+    if !is_valid_company_prefix(company_prefix, code_length) {
+        return None;
+    }
+
+    // Digits between the prefix and the check digit
+    let reference_length = code_length - 1 - company_prefix.len();
+
+    let last_reference = existing_codes
+        .iter()
+        .filter(|code| code.len() == code_length && code.starts_with(company_prefix))
+        .filter_map(|code| code[company_prefix.len()..code_length - 1].parse::<u64>().ok())
+        .max();
+
+    let next_reference = match last_reference {
+        Some(reference) => reference + 1,
+        None => 0,
+    };
+
+    if next_reference >= 10u64.pow(reference_length as u32) {
+        return None;
+    }
+
+    let body = format!("{}{:0width$}", company_prefix, next_reference, width = reference_length);
+    let check = gs1_check_digit(&body)?;
+
+    Some(format!("{}{}", body, check))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_length_and_check_digit() {
+        assert!(validate_product_code("806807071423").is_ok());
+        assert!(validate_product_code("4006381333931").is_ok());
+        assert!(validate_product_code("806807071421").is_err());
+        assert!(validate_product_code("80680707142").is_err());
+        assert!(validate_product_code("80680707142A").is_err());
+    }
+
+    #[test]
+    fn next_product_code_continues_after_highest_reference() {
+        let existing = vec!["806807071423".to_string(), "806807071430".to_string(), "4006381333931".to_string()];
+
+        assert_eq!(next_product_code("80680707", 12, &existing), Some("806807071447".to_string()));
+        assert_eq!(next_product_code("80680707", 12, &[]), Some("806807070006".to_string()));
+        assert_eq!(next_product_code("8068070714", 12, &["806807071492".to_string()]), None);
+    }
+
+    #[test]
+    fn company_prefix_is_digits_with_room_for_a_reference() {
+        assert!(is_valid_company_prefix("80680707", 12));
+        assert!(is_valid_company_prefix("8068070714", 12));
+        assert!(!is_valid_company_prefix("80680707142", 12));
+        assert!(!is_valid_company_prefix("8068-0707", 12));
+        assert!(!is_valid_company_prefix(" 80680707", 12));
+        assert!(!is_valid_company_prefix("", 12));
+        assert!(!is_valid_company_prefix("80680707", 11));
+    }
+}
@@ -1,32 +1,61 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
 use mysql_async::prelude::FromRow;
 use mysql_async::Row;
+use crate::models::incoming::take_column;
+use crate::models::product_code::validate_optional_product_code;
 
 
-#[derive(Serialize, Debug)]
+//product variant, attributes such as color, size or material are free form
+#[derive(Serialize, Debug, ToSchema)]
 pub struct Products{
     pub product_code: String,
-    pub color: String,
     pub product_name: String,
+    pub family_code: Option<String>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Products {
+    //attribute values for printing, e.g. "Peach / XL"
+    pub fn variant_description(&self) -> String {
+        self.attributes.values().map(String::as_str).collect::<Vec<_>>().join(" / ")
+    }
 }
 
 impl FromRow for Products {
     fn from_row(row: Row) -> Self{
 
-        let (product_code, color, product_name):(String, String, String) = mysql_async::from_row(row);
-
-        Products{product_code, color, product_name}
+        Products::from_row_opt(row).expect("Could not convert row to Products")
     }
 
     fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
-        let (product_code, color, product_name):(String, String, String) = mysql_async::from_row(row);
+        let mut columns = row.clone();
+
+        let product = (|| Some(Products{
+            product_code: take_column(&mut columns, "product_code")?,
+            product_name: take_column(&mut columns, "product_name")?,
+            family_code: take_column(&mut columns, "family_code")?,
+            attributes: parse_attributes(take_column(&mut columns, "attributes")?)?,
+        }))();
 
-        Ok(Products{product_code, color, product_name})
+        product.ok_or(mysql_async::FromRowError(row))
     }
 
 }
 
+// Attributes are aggregated with JSON_OBJECTAGG, products without any come back as NULL.
+// Malformed JSON gives None so the row fails to convert instead of losing its attributes
+pub(crate) fn parse_attributes(json: Option<String>) -> Option<BTreeMap<String, String>> {
+    match json {
+        Some(json) => serde_json::from_str(&json).ok(),
+        None => Some(BTreeMap::new()),
+    }
+}
+
 #[derive(Validate, Deserialize, Serialize)]
 pub struct AddProductCodeToUniqueIdentifiers{
     #[validate(length(min =1, message = "Color is required"))]
@@ -35,3 +64,170 @@ pub struct AddProductCodeToUniqueIdentifiers{
     pub product_name: String,
 }
 
+//request for creating a new product variant, product_code is generated when not given.
+//color is kept as a shorthand for the "color" attribute
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct AddProductRequest{
+    #[validate(custom(function = "validate_optional_product_code"))]
+    pub product_code: Option<String>,
+    #[validate(length(min =1, message = "Product name is required!"))]
+    pub product_name: String,
+    #[validate(length(min =1, message = "Family code can not be empty"))]
+    pub family_code: Option<String>,
+    #[validate(length(min =1, message = "Color can not be empty"))]
+    pub color: Option<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_attributes"))]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl AddProductRequest {
+    pub fn variant_attributes(&self) -> BTreeMap<String, String> {
+        merge_color(&self.attributes, self.color.as_deref())
+    }
+}
+
+// Identifies the product of a stock request, either by product_code or by name and
+// attributes. Older clients send color and product_name, which still works.
+#[derive(Validate, Deserialize, Serialize, Default, Debug, Clone, ToSchema)]
+#[validate(nested, schema(function = "validate_product_ref"))]
+pub struct ProductRef{
+    #[validate(length(min =1, message = "Product Code can not be empty"))]
+    pub product_code: Option<String>,
+    #[validate(length(min =1, message = "Product name can not be empty"))]
+    pub product_name: Option<String>,
+    #[validate(length(min =1, message = "Color can not be empty"))]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[validate(custom(function = "validate_attributes"))]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl ProductRef {
+    pub fn by_code(product_code: &str) -> ProductRef {
+        ProductRef { product_code: Some(product_code.to_string()), ..ProductRef::default() }
+    }
+
+    pub fn variant_attributes(&self) -> BTreeMap<String, String> {
+        merge_color(&self.attributes, self.color.as_deref())
+    }
+}
+
+fn validate_product_ref(product: &ProductRef) -> Result<(), ValidationError> {
+    if product.product_code.is_none() && product.product_name.is_none() {
+        let mut error = ValidationError::new("product_ref");
+        error.message = Some(Cow::from("Product code or product name is required"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn validate_attributes(attributes: &BTreeMap<String, String>) -> Result<(), ValidationError> {
+    let valid = attributes.iter().all(|(name, value)| {
+        !name.is_empty() && name.len() <= 64 && !value.is_empty() && value.len() <= 255
+    });
+
+    if !valid {
+        let mut error = ValidationError::new("attributes");
+        error.message = Some(Cow::from("Attribute names (max 64) and values (max 255) can not be empty"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn merge_color(attributes: &BTreeMap<String, String>, color: Option<&str>) -> BTreeMap<String, String> {
+    let mut merged = attributes.clone();
+    if let Some(color) = color {
+        merged.insert("color".to_string(), color.to_string());
+    }
+    merged
+}
+
+//true when the product has every requested attribute with the same value
+pub fn matches_attributes(product: &BTreeMap<String, String>, requested: &BTreeMap<String, String>) -> bool {
+    requested.iter().all(|(name, value)| product.get(name) == Some(value))
+}
+
+//family of product variants, e.g. one chair model in several colors and sizes
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ProductFamily{
+    pub family_code: String,
+    pub name: String,
+}
+
+impl FromRow for ProductFamily {
+    fn from_row(row: Row) -> Self{
+
+        let (family_code, name):(String, String) = mysql_async::from_row(row);
+
+        ProductFamily{family_code, name}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (family_code, name):(String, String) = mysql_async::from_row(row);
+
+        Ok(ProductFamily{family_code, name})
+    }
+}
+
+//request for creating a product family
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct CreateProductFamilyRequest{
+    #[validate(length(min =1, max = 64, message = "Family code must be between 1 and 64 characters"))]
+    pub family_code: String,
+    #[validate(length(min =1, message = "Family name is required"))]
+    pub name: String,
+}
+
+//get request for a single product family
+#[derive(Validate, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetProductFamily{
+    #[validate(length(min =1, message = "Family code is required"))]
+    pub family_code: String,
+}
+
+//product family with all its variants
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ProductFamilyDetails{
+    #[serde(flatten)]
+    pub family: ProductFamily,
+    pub variants: Vec<Products>,
+}
+
+//request for setting attributes of an existing product, other attributes are kept
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct SetProductAttributesRequest{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    #[validate(length(min =1, message = "At least one attribute is required"), custom(function = "validate_attributes"))]
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_shorthand_is_an_attribute() {
+        let product: ProductRef = serde_json::from_str(r#"{"product_name": "Chair", "color": "Peach", "attributes": {"size": "XL"}}"#).unwrap();
+        let attributes = product.variant_attributes();
+
+        assert!(product.validate().is_ok());
+        assert_eq!(attributes.get("color").map(String::as_str), Some("Peach"));
+        assert!(matches_attributes(&BTreeMap::from([("color".to_string(), "Peach".to_string()), ("size".to_string(), "XL".to_string()), ("material".to_string(), "Oak".to_string())]), &attributes));
+        assert!(!matches_attributes(&BTreeMap::from([("color".to_string(), "Peach".to_string())]), &attributes));
+
+        let missing: ProductRef = serde_json::from_str(r#"{"color": "Peach"}"#).unwrap();
+        assert!(missing.validate().is_err());
+    }
+
+    #[test]
+    fn malformed_attributes_are_not_dropped() {
+        assert_eq!(parse_attributes(None), Some(BTreeMap::new()));
+        assert_eq!(parse_attributes(Some(r#"{"color": "Peach"}"#.to_string())), Some(BTreeMap::from([("color".to_string(), "Peach".to_string())])));
+        assert_eq!(parse_attributes(Some(r#"{"color": "Peach""#.to_string())), None);
+    }
+}
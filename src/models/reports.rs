@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::NaiveDateTime;
+
+use crate::models::incoming::take_column;
+use crate::models::products::parse_attributes;
+
+//output format of reports, csv can be opened directly in a spreadsheet
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+//query for stock rows without outbound movement in the last N days
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SlowMovingQuery{
+    #[validate(range(min = 1, max = 3650, message = "Days must be between 1 and 3650"))]
+    pub days: i32,
+    #[validate(length(min =1, message = "Warehouse can not be empty"))]
+    pub warehouse: Option<String>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+//stock row that has not been picked from for a while
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SlowMovingStock{
+    pub product_code: String,
+    pub product_name: String,
+    pub attributes: BTreeMap<String, String>,
+    pub warehouse: String,
+    pub location: String,
+    pub lot_number: Option<String>,
+    pub pcs: i32,
+    pub last_movement_at: NaiveDateTime,
+    pub last_outbound_at: Option<NaiveDateTime>,
+    pub days_idle: i32,
+}
+
+impl FromRow for SlowMovingStock {
+    fn from_row(row: Row) -> Self{
+
+        SlowMovingStock::from_row_opt(row).expect("Could not convert row to SlowMovingStock")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let mut columns = row.clone();
+
+        let stock = (|| Some(SlowMovingStock{
+            product_code: take_column(&mut columns, "product_code")?,
+            product_name: take_column(&mut columns, "product_name")?,
+            attributes: parse_attributes(take_column(&mut columns, "attributes")?)?,
+            warehouse: take_column(&mut columns, "warehouse")?,
+            location: take_column(&mut columns, "location")?,
+            lot_number: take_column(&mut columns, "lot_number")?,
+            pcs: take_column(&mut columns, "pcs")?,
+            last_movement_at: take_column(&mut columns, "last_movement_at")?,
+            last_outbound_at: take_column(&mut columns, "last_outbound_at")?,
+            days_idle: take_column(&mut columns, "days_idle")?,
+        }))();
+
+        stock.ok_or(mysql_async::FromRowError(row))
+    }
+}
+
+//renders the slow moving report as csv with a header line
+pub fn slow_moving_csv(rows: &[SlowMovingStock]) -> String {
+    let mut csv = String::from("product_code,product_name,attributes,warehouse,location,lot_number,pcs,last_movement_at,last_outbound_at,days_idle\n");
+
+    for row in rows {
+        let fields = [
+            csv_field(&row.product_code),
+            csv_field(&row.product_name),
+            csv_field(&attributes_text(&row.attributes)),
+            csv_field(&row.warehouse),
+            csv_field(&row.location),
+            csv_field(row.lot_number.as_deref().unwrap_or_default()),
+            row.pcs.to_string(),
+            row.last_movement_at.to_string(),
+            row.last_outbound_at.map(|date| date.to_string()).unwrap_or_default(),
+            row.days_idle.to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+//attributes as "color=Peach; size=XL"
+fn attributes_text(attributes: &BTreeMap<String, String>) -> String {
+    attributes.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("; ")
+}
+
+//quotes a field when it contains a separator, quote or line break. Text starting like a
+//formula gets a leading ' so spreadsheets show it instead of evaluating it
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) { format!("'{}", value) } else { value.to_string() };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn csv_quotes_fields_with_separators() {
+        let rows = vec![SlowMovingStock {
+            product_code: "806807071423".to_string(),
+            product_name: "Chair \"Oslo\"".to_string(),
+            attributes: BTreeMap::from([("color".to_string(), "Black, matte".to_string()), ("size".to_string(), "XL".to_string())]),
+            warehouse: "HALA 5".to_string(),
+            location: "M5-A-1".to_string(),
+            lot_number: None,
+            pcs: 12,
+            last_movement_at: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(10, 0, 0).unwrap(),
+            last_outbound_at: None,
+            days_idle: 120,
+        }];
+
+        let csv = slow_moving_csv(&rows);
+        let line = csv.lines().nth(1).unwrap();
+        assert_eq!(line, "806807071423,\"Chair \"\"Oslo\"\"\",\"color=Black, matte; size=XL\",HALA 5,M5-A-1,,12,2024-01-02 10:00:00,,120");
+    }
+
+    #[test]
+    fn csv_neutralises_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"http://x\")"), "\"'=HYPERLINK(\"\"http://x\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-2"), "'-2");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("M5-A-1"), "M5-A-1");
+    }
+}
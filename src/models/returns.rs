@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+
+use crate::models::incoming::{take_column, validate_unit_cost};
+
+//condition of returned goods recorded at receiving
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReturnCondition {
+    New,
+    Opened,
+    Damaged,
+}
+
+impl ReturnCondition {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            ReturnCondition::New => "NEW",
+            ReturnCondition::Opened => "OPENED",
+            ReturnCondition::Damaged => "DAMAGED",
+        }
+    }
+}
+
+//what happens with received returns
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Disposition {
+    Restock,
+    Quarantine,
+    Scrap,
+}
+
+impl Disposition {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Disposition::Restock => "RESTOCK",
+            Disposition::Quarantine => "QUARANTINE",
+            Disposition::Scrap => "SCRAP",
+        }
+    }
+}
+
+//single product line authorized for return
+#[derive(Serialize, Deserialize, Validate, Debug, ToSchema)]
+#[validate(nested)]
+pub struct ReturnAuthorizationLine{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
+    pub pcs: i32,
+    //cost the goods were sold at, restocked returns are valued with it
+    #[serde(default)]
+    #[validate(custom(function = "validate_unit_cost"))]
+    pub unit_cost: Option<Decimal>,
+}
+
+//request for creating a return authorization (RMA) for a sales order
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct CreateReturnAuthorizationRequest{
+    #[validate(length(min =1, max = 64, message = "RMA number must be between 1 and 64 characters"))]
+    pub rma_number: String,
+    #[validate(length(min =1, message = "Sales order number is required"))]
+    pub sales_order_number: String,
+    #[validate(length(min =1, message = "Customer is required"))]
+    pub customer: String,
+    #[validate(length(min =1, message = "At least one line is required"), nested)]
+    pub lines: Vec<ReturnAuthorizationLine>,
+}
+
+//request for receiving returned goods against an RMA
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct ReceiveReturnRequest{
+    #[validate(length(min =1, message = "RMA number is required"))]
+    pub rma_number: String,
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    //warehouse the return arrives at, disposition may still put it elsewhere
+    #[validate(length(min =1, message = "Warehouse is required"))]
+    pub warehouse: String,
+    #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
+    pub pcs: i32,
+    pub condition: ReturnCondition,
+    #[validate(length(min =1, message = "Reason is required"))]
+    pub reason: String,
+    #[validate(length(min =1, message = "Lot number can not be empty"))]
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    #[validate(length(min =1, message = "Serial numbers can not be an empty list"))]
+    pub serial_numbers: Option<Vec<String>>,
+}
+
+//request for deciding what happens with a received return
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+#[validate(schema(function = "validate_disposition_location"))]
+pub struct DispositionRequest{
+    #[validate(range(min = 1, message = "Receipt id is required"))]
+    pub receipt_id: u64,
+    pub disposition: Disposition,
+    pub warehouse: Option<String>,
+    pub location: Option<String>,
+}
+
+fn validate_disposition_location(request: &DispositionRequest) -> Result<(), ValidationError> {
+    let has_location = request.warehouse.as_deref().is_some_and(|warehouse| !warehouse.is_empty())
+        && request.location.as_deref().is_some_and(|location| !location.is_empty());
+
+    if request.disposition != Disposition::Scrap && !has_location {
+        let mut error = ValidationError::new("disposition_location");
+        error.message = Some(Cow::from("Warehouse and location are required for restock and quarantine"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+//get request for a single RMA
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetReturnAuthorization{
+    #[validate(length(min =1, message = "RMA number is required"))]
+    pub rma_number: String,
+}
+
+//return authorization header
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReturnAuthorization{
+    pub rma_number: String,
+    pub sales_order_number: String,
+    pub customer: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl FromRow for ReturnAuthorization {
+    fn from_row(row: Row) -> Self{
+
+        let (rma_number, sales_order_number, customer, created_at):(String, String, String, NaiveDateTime) = mysql_async::from_row(row);
+
+        ReturnAuthorization{rma_number, sales_order_number, customer, created_at}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (rma_number, sales_order_number, customer, created_at):(String, String, String, NaiveDateTime) = mysql_async::from_row(row);
+
+        Ok(ReturnAuthorization{rma_number, sales_order_number, customer, created_at})
+    }
+}
+
+//authorized line with the quantity received so far
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReturnLineStatus{
+    pub product_code: String,
+    pub pcs_authorized: i32,
+    pub pcs_received: i32,
+}
+
+impl FromRow for ReturnLineStatus {
+    fn from_row(row: Row) -> Self{
+
+        let (product_code, pcs_authorized, pcs_received):(String, i32, i32) = mysql_async::from_row(row);
+
+        ReturnLineStatus{product_code, pcs_authorized, pcs_received}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (product_code, pcs_authorized, pcs_received):(String, i32, i32) = mysql_async::from_row(row);
+
+        Ok(ReturnLineStatus{product_code, pcs_authorized, pcs_received})
+    }
+}
+
+//received return with its condition, reason and disposition
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReturnReceipt{
+    pub receipt_id: u64,
+    pub product_code: String,
+    //None for receipts recorded before the receiving warehouse was kept
+    pub warehouse: Option<String>,
+    pub pcs: i32,
+    pub condition: String,
+    pub reason: String,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub received_at: NaiveDateTime,
+    pub disposition: Option<String>,
+    pub disposition_warehouse: Option<String>,
+    pub disposition_location: Option<String>,
+}
+
+impl FromRow for ReturnReceipt {
+    fn from_row(row: Row) -> Self{
+
+        ReturnReceipt::from_row_opt(row).expect("Could not convert row to ReturnReceipt")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let mut columns = row.clone();
+
+        let receipt = (|| Some(ReturnReceipt{
+            receipt_id: take_column(&mut columns, "id")?,
+            product_code: take_column(&mut columns, "product_code")?,
+            warehouse: take_column(&mut columns, "warehouse")?,
+            pcs: take_column(&mut columns, "pcs")?,
+            condition: take_column(&mut columns, "item_condition")?,
+            reason: take_column(&mut columns, "reason")?,
+            lot_number: take_column(&mut columns, "lot_number")?,
+            expiry_date: take_column(&mut columns, "expiry_date")?,
+            received_at: take_column(&mut columns, "received_at")?,
+            disposition: take_column(&mut columns, "disposition")?,
+            disposition_warehouse: take_column(&mut columns, "disposition_warehouse")?,
+            disposition_location: take_column(&mut columns, "disposition_location")?,
+        }))();
+
+        receipt.ok_or(mysql_async::FromRowError(row))
+    }
+}
+
+//RMA with lines and receipts
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReturnAuthorizationDetails{
+    #[serde(flatten)]
+    pub authorization: ReturnAuthorization,
+    pub status: String,
+    pub lines: Vec<ReturnLineStatus>,
+    pub receipts: Vec<ReturnReceipt>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disposition_requires_location_unless_scrapped() {
+        let mut request = DispositionRequest { receipt_id: 1, disposition: Disposition::Restock, warehouse: Some("HALA 5".to_string()), location: None };
+        assert!(request.validate().is_err());
+
+        request.location = Some("M5-A-1".to_string());
+        assert!(request.validate().is_ok());
+
+        request.disposition = Disposition::Scrap;
+        request.warehouse = None;
+        request.location = None;
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn line_unit_cost_is_optional_and_non_negative() {
+        let line: ReturnAuthorizationLine = serde_json::from_str(r#"{"product_code": "806807071423", "pcs": 2}"#).unwrap();
+        assert_eq!(line.unit_cost, None);
+        assert!(line.validate().is_ok());
+
+        let line: ReturnAuthorizationLine = serde_json::from_str(r#"{"product_code": "806807071423", "pcs": 2, "unit_cost": -1.5}"#).unwrap();
+        assert!(line.validate().is_err());
+    }
+}
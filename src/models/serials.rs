@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::NaiveDateTime;
+
+//current state of a single serial number
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SerialNumber{
+    pub serial_number: String,
+    pub product_code: String,
+    pub warehouse: Option<String>,
+    pub location: Option<String>,
+    pub status: String,
+    pub customer: Option<String>,
+}
+
+impl FromRow for SerialNumber {
+    fn from_row(row: Row) -> Self{
+
+        let (serial_number, product_code, warehouse, location, status, customer):(String, String, Option<String>, Option<String>, String, Option<String>) = mysql_async::from_row(row);
+
+        SerialNumber{serial_number, product_code, warehouse, location, status, customer}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (serial_number, product_code, warehouse, location, status, customer):(String, String, Option<String>, Option<String>, String, Option<String>) = mysql_async::from_row(row);
+
+        Ok(SerialNumber{serial_number, product_code, warehouse, location, status, customer})
+    }
+}
+
+//single entry in the movement history of a serial number
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SerialMovement{
+    pub movement_type: String,
+    pub warehouse: String,
+    pub location: String,
+    pub customer: Option<String>,
+    pub moved_at: NaiveDateTime,
+}
+
+impl FromRow for SerialMovement {
+    fn from_row(row: Row) -> Self{
+
+        let (movement_type, warehouse, location, customer, moved_at):(String, String, String, Option<String>, NaiveDateTime) = mysql_async::from_row(row);
+
+        SerialMovement{movement_type, warehouse, location, customer, moved_at}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (movement_type, warehouse, location, customer, moved_at):(String, String, String, Option<String>, NaiveDateTime) = mysql_async::from_row(row);
+
+        Ok(SerialMovement{movement_type, warehouse, location, customer, moved_at})
+    }
+}
+
+//serial number lookup response: current location and full history
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SerialNumberDetails{
+    #[serde(flatten)]
+    pub serial: SerialNumber,
+    pub history: Vec<SerialMovement>,
+}
+
+//get request for a single serial number
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetSerialNumber{
+    #[validate(length(min =1, message = "Serial number is required"))]
+    pub serial_number: String,
+}
+
+//request for turning serial tracking on or off for a product
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct SetSerialTracking{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    pub serial_tracked: bool,
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::NaiveDate;
+
+use crate::models::products::{parse_attributes, ProductRef};
+
+//status of a stock row, only available stock can be allocated
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StockStatus {
+    #[default]
+    Available,
+    Quarantine,
+    Blocked,
+    Damaged,
+}
+
+impl StockStatus {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            StockStatus::Available => "AVAILABLE",
+            StockStatus::Quarantine => "QUARANTINE",
+            StockStatus::Blocked => "BLOCKED",
+            StockStatus::Damaged => "DAMAGED",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Option<StockStatus> {
+        match value {
+            "AVAILABLE" => Some(StockStatus::Available),
+            "QUARANTINE" => Some(StockStatus::Quarantine),
+            "BLOCKED" => Some(StockStatus::Blocked),
+            "DAMAGED" => Some(StockStatus::Damaged),
+            _ => None,
+        }
+    }
+}
+
+//request for moving a quantity of a stock row from one status to another
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct ChangeStockStatusRequest{
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub product: ProductRef,
+    #[validate(length(min =1, message = "Warehouse is required"))]
+    pub warehouse: String,
+    #[validate(length(min =1, message = "Location is required"))]
+    pub location: String,
+    #[validate(length(min =1, message = "Lot number can not be empty"))]
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub from_status: StockStatus,
+    pub to_status: StockStatus,
+    #[validate(range(min = 1, max = 10000, message = "PCS must be between 1 and 10000"))]
+    pub pcs: i32,
+}
+
+//available quantity of a product, stock with any other status is left out
+#[derive(Serialize, Debug, ToSchema)]
+pub struct AvailableStock{
+    pub product_code: String,
+    pub product_name: String,
+    pub attributes: BTreeMap<String, String>,
+    pub available_pcs: i64,
+}
+
+impl FromRow for AvailableStock {
+    fn from_row(row: Row) -> Self{
+
+        AvailableStock::from_row_opt(row).expect("Could not convert row to AvailableStock")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (product_code, product_name, attributes, available_pcs):(String, String, Option<String>, i64) = mysql_async::from_row_opt(row.clone())?;
+
+        match parse_attributes(attributes) {
+            Some(attributes) => Ok(AvailableStock{product_code, product_name, attributes, available_pcs}),
+            None => Err(mysql_async::FromRowError(row)),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+
+use crate::auth::Principal;
+use crate::models::errors::{ApiError, FieldError};
+
+// Header carrying the tenant of requests made without credentials, i.e. /auth/login
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+
+//company the request is made for, every query in Database is scoped to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant(String);
+
+impl Tenant {
+    //tenant ids are 1 to 64 letters, digits, '-' or '_'
+    pub fn new(id: &str) -> Option<Tenant> {
+        let valid = !id.is_empty()
+            && id.len() <= 64
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        valid.then(|| Tenant(id.to_string()))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequest for Tenant {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    // Authenticated requests always use the tenant of the user or API key, the header is ignored
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(principal) = req.extensions().get::<Principal>() {
+            return ready(Ok(principal.tenant.clone()));
+        }
+
+        let tenant = req.headers()
+            .get(TENANT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| Tenant::new(value.trim()));
+
+        ready(tenant.ok_or_else(|| {
+            ApiError::MalformedRequest(vec![FieldError::new(TENANT_HEADER, "invalid_tenant", "Missing or invalid X-Tenant-Id header.")])
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_ids_are_restricted_to_safe_characters() {
+        assert_eq!(Tenant::new("mega-plast_1").map(|t| t.id().to_string()), Some("mega-plast_1".to_string()));
+        assert!(Tenant::new("").is_none());
+        assert!(Tenant::new("a b").is_none());
+        assert!(Tenant::new(&"x".repeat(65)).is_none());
+    }
+
+    #[actix_web::test]
+    async fn missing_tenant_header_is_a_malformed_request() {
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        let error = Tenant::extract(&req).await.unwrap_err();
+
+        assert_eq!(error.code(), "malformed_request");
+        assert_eq!(error.field_errors()[0].field, TENANT_HEADER);
+    }
+}
@@ -0,0 +1,265 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+
+//what a user or API key may do, every role includes the rights of the roles before it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    //reads stock, reports and labels
+    #[default]
+    Viewer,
+    //adds, removes and moves stock in the assigned warehouses
+    Operator,
+    //returns, packaging, serial tracking, cost methods and ABC analysis
+    Manager,
+    //product catalog, users and API keys
+    Admin,
+}
+
+impl Role {
+    //unknown values in the database fall back to the least rights
+    pub fn parse(value: &str) -> Role {
+        match value {
+            "operator" => Role::Operator,
+            "manager" => Role::Manager,
+            "admin" => Role::Admin,
+            _ => Role::Viewer,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Manager => "manager",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+//warehouses a user or API key changes stock in. Without all_warehouses only the listed ones,
+//so an empty list gives no warehouse at all
+#[derive(Serialize, Deserialize, Validate, Clone, Debug, Default, PartialEq, ToSchema)]
+#[validate(nested)]
+pub struct WarehouseAccess{
+    #[serde(default)]
+    pub all_warehouses: bool,
+    #[serde(default)]
+    #[validate(custom(function = "validate_warehouses"))]
+    pub warehouses: Vec<String>,
+}
+
+impl WarehouseAccess {
+    pub fn all() -> WarehouseAccess {
+        WarehouseAccess { all_warehouses: true, warehouses: Vec::new() }
+    }
+
+    pub fn allows(&self, warehouse: &str) -> bool {
+        self.all_warehouses || self.warehouses.iter().any(|assigned| assigned == warehouse)
+    }
+}
+
+//warehouse staff member that can log in, the password hash never leaves the database layer
+#[derive(Serialize, Debug, ToSchema)]
+pub struct User{
+    pub username: String,
+    pub role: Role,
+    #[serde(flatten)]
+    pub access: WarehouseAccess,
+    pub created_at: NaiveDateTime,
+}
+
+impl FromRow for User {
+    fn from_row(row: Row) -> Self{
+
+        let (username, role, all_warehouses, created_at):(String, String, bool, NaiveDateTime) = mysql_async::from_row(row);
+
+        User{username, role: Role::parse(&role), access: WarehouseAccess { all_warehouses, warehouses: Vec::new() }, created_at}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (username, role, all_warehouses, created_at):(String, String, bool, NaiveDateTime) = mysql_async::from_row(row);
+
+        Ok(User{username, role: Role::parse(&role), access: WarehouseAccess { all_warehouses, warehouses: Vec::new() }, created_at})
+    }
+}
+
+//POST /auth/login body, the tenant comes from the X-Tenant-Id header
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct LoginRequest{
+    #[validate(length(min =1, message = "Username is required"))]
+    pub username: String,
+    #[validate(length(min =1, message = "Password is required"))]
+    pub password: String,
+}
+
+//issued JWT, sent back as Authorization: Bearer <token>
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LoginResponse{
+    pub token: String,
+    pub token_type: &'static str,
+    pub expires_at: DateTime<Utc>,
+}
+
+//request for creating a user, e.g. an operator that only works in "HALA 5"
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct CreateUserRequest{
+    #[validate(custom(function = "validate_username"))]
+    pub username: String,
+    #[validate(length(min = 12, max = 128, message = "Password must be between 12 and 128 characters"))]
+    pub password: String,
+    #[serde(default)]
+    pub role: Role,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub access: WarehouseAccess,
+}
+
+//request for changing the role and warehouses of a user
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct SetUserAccessRequest{
+    pub role: Role,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub access: WarehouseAccess,
+}
+
+fn validate_warehouses(warehouses: &[String]) -> Result<(), ValidationError> {
+    if warehouses.iter().any(|warehouse| warehouse.is_empty() || warehouse.len() > 255) {
+        let mut error = ValidationError::new("warehouses");
+        error.message = Some(Cow::from("Warehouses must be between 1 and 255 characters"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+//request for setting a new password
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct ChangePasswordRequest{
+    #[validate(length(min = 12, max = 128, message = "Password must be between 12 and 128 characters"))]
+    pub password: String,
+}
+
+//path of a single user
+#[derive(Validate, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetUser{
+    #[validate(custom(function = "validate_username"))]
+    pub username: String,
+}
+
+//usernames are 3 to 64 letters, digits, '.', '_', '-' or '@'
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let valid = (3..=64).contains(&username.len())
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '@'));
+
+    if !valid {
+        let mut error = ValidationError::new("username");
+        error.message = Some(Cow::from("Username must be 3 to 64 letters, digits, '.', '_', '-' or '@'"));
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+//API key of a machine integration, the secret part is only shown once when created
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ApiKey{
+    pub key_id: String,
+    pub name: String,
+    pub role: Role,
+    #[serde(flatten)]
+    pub access: WarehouseAccess,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl FromRow for ApiKey {
+    fn from_row(row: Row) -> Self{
+
+        let (key_id, name, role, all_warehouses, created_by, created_at, revoked_at):(String, String, String, bool, String, NaiveDateTime, Option<NaiveDateTime>) = mysql_async::from_row(row);
+
+        ApiKey{key_id, name, role: Role::parse(&role), access: WarehouseAccess { all_warehouses, warehouses: Vec::new() }, created_by, created_at, revoked_at}
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let (key_id, name, role, all_warehouses, created_by, created_at, revoked_at):(String, String, String, bool, String, NaiveDateTime, Option<NaiveDateTime>) = mysql_async::from_row(row);
+
+        Ok(ApiKey{key_id, name, role: Role::parse(&role), access: WarehouseAccess { all_warehouses, warehouses: Vec::new() }, created_by, created_at, revoked_at})
+    }
+}
+
+//request for creating an API key, e.g. {"name": "ERP integration", "role": "operator", "warehouses": ["HALA 5"]}.
+//Like users, keys only change stock in the listed warehouses unless all_warehouses is set
+#[derive(Validate, Deserialize, Serialize, ToSchema)]
+pub struct CreateApiKeyRequest{
+    #[validate(length(min =1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    #[serde(default = "default_api_key_role")]
+    pub role: Role,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub access: WarehouseAccess,
+}
+
+fn default_api_key_role() -> Role {
+    Role::Operator
+}
+
+//new API key with its secret, sent as X-Api-Key: <key>
+#[derive(Serialize, Debug, ToSchema)]
+pub struct CreatedApiKey{
+    pub key_id: String,
+    pub name: String,
+    pub role: Role,
+    #[serde(flatten)]
+    pub access: WarehouseAccess,
+    pub key: String,
+}
+
+//path of a single API key
+#[derive(Validate, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetApiKey{
+    #[validate(length(equal = 16, message = "Key id must be 16 characters"))]
+    pub key_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_and_passwords_are_checked() {
+        let user: CreateUserRequest = serde_json::from_str(r#"{"username": "marko.m@hala5", "password": "correct horse battery", "role": "operator", "warehouses": ["HALA 5"]}"#).unwrap();
+        assert!(user.validate().is_ok());
+        assert!(user.role > Role::Viewer && user.role < Role::Manager);
+
+        let short: CreateUserRequest = serde_json::from_str(r#"{"username": "mm", "password": "secret"}"#).unwrap();
+        let errors = short.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("username"));
+        assert!(errors.field_errors().contains_key("password"));
+    }
+
+    #[test]
+    fn no_warehouses_means_no_access() {
+        let user: CreateUserRequest = serde_json::from_str(r#"{"username": "marko", "password": "correct horse battery", "role": "operator"}"#).unwrap();
+        assert_eq!(user.access, WarehouseAccess::default());
+        assert!(!user.access.allows("HALA 5"));
+
+        let assigned = WarehouseAccess { all_warehouses: false, warehouses: vec!["HALA 5".to_string()] };
+        assert!(assigned.allows("HALA 5") && !assigned.allows("HALA 1"));
+        assert!(WarehouseAccess::all().allows("HALA 1"));
+
+        let blank: SetUserAccessRequest = serde_json::from_str(r#"{"role": "operator", "warehouses": [""]}"#).unwrap();
+        assert!(blank.validate().is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use mysql_async::prelude::FromRow;
+use mysql_async::Row;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::models::incoming::take_column;
+
+//cost method configured per product
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CostMethod {
+    #[default]
+    Fifo,
+    WeightedAverage,
+}
+
+impl CostMethod {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            CostMethod::Fifo => "FIFO",
+            CostMethod::WeightedAverage => "WEIGHTED_AVERAGE",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Option<CostMethod> {
+        match value {
+            "FIFO" => Some(CostMethod::Fifo),
+            "WEIGHTED_AVERAGE" => Some(CostMethod::WeightedAverage),
+            _ => None,
+        }
+    }
+}
+
+//request for setting the cost method of a product
+#[derive(Serialize,Deserialize,Validate,ToSchema)]
+pub struct SetCostMethod{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+    pub cost_method: CostMethod,
+}
+
+//get request for the cost layers of a product
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct GetCostLayers{
+    #[validate(length(min =1, message = "Product Code is required"))]
+    pub product_code: String,
+}
+
+//query for the cost layers, optionally limited to one warehouse
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CostLayersQuery{
+    #[validate(length(min =1, message = "Warehouse can not be empty"))]
+    pub warehouse: Option<String>,
+}
+
+//query for the valuation report, date defaults to today
+#[derive(Serialize,Deserialize,Validate,IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ValuationQuery{
+    pub date: Option<NaiveDate>,
+    #[validate(length(min =1, message = "Warehouse can not be empty"))]
+    pub warehouse: Option<String>,
+    #[validate(length(min =1, message = "Product Code can not be empty"))]
+    pub product_code: Option<String>,
+}
+
+//receipt (positive pcs, with unit cost) or issue (negative pcs) of a product in a warehouse
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CostMovement{
+    pub product_code: String,
+    pub warehouse: String,
+    pub pcs: i32,
+    pub unit_cost: Option<Decimal>,
+    pub moved_at: NaiveDateTime,
+}
+
+impl FromRow for CostMovement {
+    fn from_row(row: Row) -> Self{
+
+        CostMovement::from_row_opt(row).expect("Could not convert row to CostMovement")
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, mysql_async::FromRowError> {
+        let mut columns = row.clone();
+
+        let movement = (|| Some(CostMovement{
+            product_code: take_column(&mut columns, "product_code")?,
+            warehouse: take_column(&mut columns, "warehouse")?,
+            pcs: take_column(&mut columns, "pcs")?,
+            unit_cost: take_column(&mut columns, "unit_cost")?,
+            moved_at: take_column(&mut columns, "moved_at")?,
+        }))();
+
+        movement.ok_or(mysql_async::FromRowError(row))
+    }
+}
+
+//remaining quantity of a single receipt
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct CostLayer{
+    pub warehouse: String,
+    pub received_at: NaiveDateTime,
+    pub unit_cost: Decimal,
+    pub pcs: i32,
+}
+
+//value of a product in a warehouse
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct ValuationRow{
+    pub product_code: String,
+    pub warehouse: String,
+    pub cost_method: CostMethod,
+    pub pcs: i32,
+    pub unit_cost: Decimal,
+    pub value: Decimal,
+}
+
+//valuation report response
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ValuationReport{
+    pub date: NaiveDate,
+    pub total_value: Decimal,
+    pub rows: Vec<ValuationRow>,
+}
+
+// Replays receipts and issues of one product in one warehouse, issues consume
+// the oldest receipts first. Issues without remaining layers are ignored.
+pub fn fifo_layers(movements: &[CostMovement]) -> Vec<CostLayer> {
+    let mut layers: Vec<CostLayer> = Vec::new();
+
+    for movement in movements {
+        if movement.pcs > 0 {
+            layers.push(CostLayer {
+                warehouse: movement.warehouse.clone(),
+                received_at: movement.moved_at,
+                unit_cost: movement.unit_cost.unwrap_or_default(),
+                pcs: movement.pcs,
+            });
+            continue;
+        }
+
+        let mut to_issue = -movement.pcs;
+        for layer in layers.iter_mut().filter(|layer| layer.pcs > 0) {
+            let taken = to_issue.min(layer.pcs);
+            layer.pcs -= taken;
+            to_issue -= taken;
+
+            if to_issue == 0 {
+                break;
+            }
+        }
+    }
+
+    layers.retain(|layer| layer.pcs > 0);
+    layers
+}
+
+// Moving average: every receipt blends into the average, issues leave it unchanged.
+pub fn weighted_average(movements: &[CostMovement]) -> (i32, Decimal) {
+    let mut pcs = 0;
+    let mut unit_cost = Decimal::ZERO;
+
+    for movement in movements {
+        if movement.pcs > 0 {
+            let received_cost = movement.unit_cost.unwrap_or_default();
+            unit_cost = (Decimal::from(pcs) * unit_cost + Decimal::from(movement.pcs) * received_cost) / Decimal::from(pcs + movement.pcs);
+            pcs += movement.pcs;
+        } else {
+            pcs = (pcs + movement.pcs).max(0);
+        }
+    }
+
+    (pcs, unit_cost)
+}
+
+//quantity and value of one product in one warehouse with the given method
+pub fn value_of(method: CostMethod, movements: &[CostMovement]) -> (i32, Decimal) {
+    match method {
+        CostMethod::Fifo => fifo_layers(movements)
+            .iter()
+            .fold((0, Decimal::ZERO), |(pcs, value), layer| (pcs + layer.pcs, value + Decimal::from(layer.pcs) * layer.unit_cost)),
+        CostMethod::WeightedAverage => {
+            let (pcs, unit_cost) = weighted_average(movements);
+            (pcs, Decimal::from(pcs) * unit_cost)
+        }
+    }
+}
+
+// Builds the report from movements ordered by product and warehouse, methods are
+// looked up per product. Product/warehouse pairs with nothing left are skipped.
+pub fn valuation_rows(movements: &[CostMovement], method_of: impl Fn(&str) -> CostMethod) -> Vec<ValuationRow> {
+    let mut rows = Vec::new();
+
+    for group in movements.chunk_by(|a, b| a.product_code == b.product_code && a.warehouse == b.warehouse) {
+        let cost_method = method_of(&group[0].product_code);
+        let (pcs, value) = value_of(cost_method, group);
+
+        if pcs == 0 {
+            continue;
+        }
+
+        rows.push(ValuationRow {
+            product_code: group[0].product_code.clone(),
+            warehouse: group[0].warehouse.clone(),
+            cost_method,
+            pcs,
+            unit_cost: round_to(value / Decimal::from(pcs), 4),
+            value: round_to(value, 2),
+        });
+    }
+
+    rows
+}
+
+// Half away from zero like accounting, Decimal rounds half to even by default
+pub fn round_to(value: Decimal, decimals: u32) -> Decimal {
+    value.round_dp_with_strategy(decimals, RoundingStrategy::MidpointAwayFromZero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movement(day: u32, pcs: i32, unit_cost: Option<&str>) -> CostMovement {
+        CostMovement {
+            product_code: "806807071423".to_string(),
+            warehouse: "HALA 5".to_string(),
+            pcs,
+            unit_cost: unit_cost.map(|cost| cost.parse().unwrap()),
+            moved_at: NaiveDate::from_ymd_opt(2024, 3, day).unwrap().and_hms_opt(8, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn fifo_issues_consume_oldest_layers_first() {
+        let movements = vec![movement(1, 10, Some("2.00")), movement(2, 10, Some("3.00")), movement(3, -15, None)];
+
+        let layers = fifo_layers(&movements);
+        assert_eq!(layers.len(), 1);
+        assert_eq!((layers[0].pcs, layers[0].unit_cost), (5, Decimal::new(3, 0)));
+        assert_eq!(value_of(CostMethod::Fifo, &movements), (5, Decimal::new(15, 0)));
+    }
+
+    #[test]
+    fn weighted_average_blends_receipts_and_keeps_cost_on_issue() {
+        let movements = vec![movement(1, 10, Some("2.00")), movement(2, 10, Some("3.00")), movement(3, -15, None)];
+
+        assert_eq!(weighted_average(&movements), (5, Decimal::new(25, 1)));
+        assert_eq!(value_of(CostMethod::WeightedAverage, &movements), (5, Decimal::new(125, 1)));
+    }
+
+    #[test]
+    fn values_are_exact_to_the_cent() {
+        // 0.1 and 0.2 have no exact binary form, ten receipts of each still add up to 3.00
+        let movements: Vec<CostMovement> = (1..=20).map(|day| movement(day, 1, Some(if day % 2 == 0 { "0.10" } else { "0.20" }))).collect();
+        let rows = valuation_rows(&movements, |_| CostMethod::Fifo);
+
+        assert_eq!(rows[0].value, Decimal::new(300, 2));
+        assert_eq!(rows[0].unit_cost, Decimal::new(15, 2));
+        assert_eq!(round_to(Decimal::new(125, 3), 2), Decimal::new(13, 2));
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use actix_web::http::Method;
+use tokio::time::Instant;
+
+use crate::db::database::Database;
+use crate::models::errors::ApiError;
+
+// How often the drain looks at the number of mutations still in flight
+const DRAIN_POLL: Duration = Duration::from_millis(50);
+
+// Stock mutations (POST, PUT, PATCH, DELETE) being handled right now. On SIGINT or SIGTERM the
+// server stops accepting connections and new mutations, and the shutdown waits until this drops
+// to zero before the workers are stopped, so a removal is never cut off between its UPDATE and DELETE
+#[derive(Clone, Default)]
+pub struct InFlightMutations{
+    count: Arc<AtomicUsize>,
+    draining: Arc<AtomicBool>,
+}
+
+//counts one mutation until dropped, also when the request is cancelled
+pub struct MutationGuard(Arc<AtomicUsize>);
+
+impl InFlightMutations {
+    // Mutations are refused once the drain started, also those sent on open keep-alive connections
+    pub fn track(&self, method: &Method) -> Result<Option<MutationGuard>, ApiError> {
+        if !matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE) {
+            return Ok(None);
+        }
+
+        // Counted before the flag is read, so the drain never sees zero while one is starting
+        let guard = MutationGuard(self.count.clone());
+        self.count.fetch_add(1, Ordering::SeqCst);
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(ApiError::ShuttingDown);
+        }
+
+        Ok(Some(guard))
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    //waits until no mutation is in flight, the mutations still running after the timeout are returned
+    pub async fn drain(&self, timeout: Duration) -> Result<(), usize> {
+        let deadline = Instant::now() + timeout;
+
+        while self.count() > 0 {
+            if Instant::now() >= deadline {
+                return Err(self.count());
+            }
+            tokio::time::sleep(DRAIN_POLL).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for MutationGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Signals are handled here instead of by actix, so the workers are only stopped once the
+// mutations in flight finished or shutdown_timeout_secs passed
+pub async fn on_signal(server: ServerHandle, in_flight: InFlightMutations, timeout: Duration) {
+    wait_for_signal().await;
+    tracing::info!(in_flight = in_flight.count(), "Shutdown signal received, no new connections are accepted");
+    in_flight.start_draining();
+    server.pause().await;
+
+    let drained = match in_flight.drain(timeout).await {
+        Ok(()) => {
+            tracing::info!("All stock mutations finished");
+            true
+        }
+        Err(cancelled) => {
+            tracing::warn!(cancelled, "Stock mutations did not finish within the shutdown timeout and are rolled back");
+            false
+        }
+    };
+
+    // Other requests still get the shutdown timeout, cancelled mutations are stopped right away
+    server.stop(drained).await;
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "SIGTERM can not be handled, only Ctrl+C stops the server");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+// Runs once the workers stopped: closes the pool, which also ends its background task for idle connections
+pub async fn close(db: &Database, timeout: Duration) {
+    // disconnect waits for every connection taken from the pool to be returned
+    match tokio::time::timeout(timeout, db.pool.clone().disconnect()).await {
+        Ok(Ok(())) => tracing::info!("Database pool disconnected"),
+        Ok(Err(err)) => tracing::error!(error = %err, "Error disconnecting the database pool"),
+        Err(_) => tracing::error!(timeout_secs = timeout.as_secs(), "Database pool did not disconnect within the timeout"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_mutations_are_counted_until_dropped() {
+        let in_flight = InFlightMutations::default();
+
+        let removal = in_flight.track(&Method::DELETE).unwrap();
+        let listing = in_flight.track(&Method::GET).unwrap();
+        assert!(listing.is_none());
+        assert_eq!(in_flight.count(), 1);
+
+        drop(removal);
+        assert_eq!(in_flight.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_mutations_up_to_the_timeout() {
+        let in_flight = InFlightMutations::default();
+
+        let removal = in_flight.track(&Method::DELETE).unwrap();
+        let finishing = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            drop(removal);
+        });
+        assert_eq!(in_flight.drain(Duration::from_secs(5)).await, Ok(()));
+        finishing.await.unwrap();
+
+        let _stuck = in_flight.track(&Method::POST).unwrap();
+        assert_eq!(in_flight.drain(Duration::from_millis(100)).await, Err(1));
+    }
+
+    #[test]
+    fn mutations_are_refused_once_draining() {
+        let in_flight = InFlightMutations::default();
+        in_flight.start_draining();
+
+        assert!(matches!(in_flight.track(&Method::POST), Err(ApiError::ShuttingDown)));
+        assert_eq!(in_flight.count(), 0);
+        assert!(in_flight.track(&Method::GET).unwrap().is_none());
+    }
+}